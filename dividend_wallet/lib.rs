@@ -4,13 +4,13 @@ use ink::primitives::AccountId;
 use sp_runtime::MultiAddress;
 
 // Export DividendWallet so that it can be used in zeit_fund
-pub use self::dividend_wallet::DividendWalletRef;
+pub use self::dividend_wallet::{DividendWalletRef, Error};
 
 #[ink::contract]
 mod dividend_wallet {
     // use core::fmt::{Debug, Formatter};
 
-    use crate::{AssetManagerCall, RuntimeCall};
+    use crate::{AssetManagerCall, EnvFailure, RuntimeCall};
 
     /// Defines the storage of your contract.
     /// Add new fields to the below struct in order
@@ -21,6 +21,35 @@ mod dividend_wallet {
         fund: AccountId,
     }
 
+    /// Event emitted when a dividend is sent to a user.
+    #[ink(event)]
+    pub struct Distributed {
+        #[ink(topic)]
+        dest: AccountId,
+        currency_id: crate::ZeitgeistAsset,
+        amount: u128,
+    }
+
+    /// Event emitted when the fund replaces the wallet's code.
+    #[ink(event)]
+    pub struct Upgraded {
+        code_hash: Hash,
+    }
+
+    /// The dividend wallet error types.
+    #[derive(Debug, PartialEq, Eq, scale::Encode, scale::Decode)]
+    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+    pub enum Error {
+        /// Returned if the caller is not the wallet's fund.
+        Unauthorized,
+        /// Returned if the wallet does not hold enough ZTG for the distribution.
+        InsufficientFunds,
+        /// Returned if the runtime rejected the transfer.
+        TransferFailed(EnvFailure),
+        /// Returned if the wallet's code could not be replaced.
+        UpgradeFailed(EnvFailure),
+    }
+
     impl DividendWallet {
         /// Constructor that initializes the `bool` value to the given `init_value`.
         #[ink(constructor, payable)]
@@ -35,22 +64,59 @@ mod dividend_wallet {
             self.fund
         }
 
+        /// Sends `amount` of `currency_id` to `dest`. Only the fund can distribute.
+        ///
+        /// On success a `Distributed` event is emitted.
         #[ink(message)]
-        pub fn distribute(&mut self, dest: AccountId, amount: u128) -> bool {
+        pub fn distribute(
+            &mut self,
+            dest: AccountId,
+            currency_id: crate::ZeitgeistAsset,
+            amount: u128,
+        ) -> Result<(), Error> {
             if self.env().caller() != self.fund {
-                ink::env::debug_println!("Caller of DividendWallet was not its fund!");
-                return false;
+                return Err(Error::Unauthorized);
             }
 
-            let res =
-                self.env()
-                    .call_runtime(&RuntimeCall::AssetManager(AssetManagerCall::Transfer {
-                        dest: dest.into(),
-                        currency_id: crate::ZeitgeistAsset::Ztg,
-                        amount,
-                    }));
-
-            !res.is_err()
+            // Only the ZTG balance can be checked from within a contract
+            if currency_id == crate::ZeitgeistAsset::Ztg && self.env().balance() < amount {
+                return Err(Error::InsufficientFunds);
+            }
+
+            self.env()
+                .call_runtime(&RuntimeCall::AssetManager(AssetManagerCall::Transfer {
+                    dest: dest.into(),
+                    currency_id: currency_id.clone(),
+                    amount,
+                }))
+                .map_err(|e| Error::TransferFailed(EnvFailure::from(e)))?;
+
+            self.env().emit_event(Distributed {
+                dest,
+                currency_id,
+                amount,
+            });
+
+            Ok(())
+        }
+
+        /// Replaces the wallet's code with the code at `code_hash`. Only the fund can upgrade
+        /// the wallet, after its shareholders vote for it.
+        ///
+        /// New code must keep the `fund` field first in storage, and only add fields after it.
+        #[ink(message)]
+        pub fn upgrade(&mut self, code_hash: Hash) -> Result<(), Error> {
+            if self.env().caller() != self.fund {
+                return Err(Error::Unauthorized);
+            }
+
+            self.env()
+                .set_code_hash(&code_hash)
+                .map_err(|e| Error::UpgradeFailed(EnvFailure::from(e)))?;
+
+            self.env().emit_event(Upgraded { code_hash });
+
+            Ok(())
         }
     }
 
@@ -59,8 +125,9 @@ mod dividend_wallet {
     /// The below code is technically just normal Rust code.
     #[cfg(test)]
     mod tests {
-        use super::DividendWallet;
+        use super::{DividendWallet, Error};
         use crate::dividend_wallet::Environment;
+        use crate::EnvFailure;
         use ink::primitives::AccountId;
 
         #[ink::test]
@@ -70,11 +137,131 @@ mod dividend_wallet {
             let contract = DividendWallet::new();
             assert_eq!(contract.fund(), fund);
         }
+
+        #[ink::test]
+        fn distribute_rejects_other_callers() {
+            let fund = AccountId::from([0x01; 32]);
+            ink::env::test::set_caller::<Environment>(fund);
+            let mut contract = DividendWallet::new();
+
+            ink::env::test::set_caller::<Environment>(AccountId::from([0x02; 32]));
+            let res = contract.distribute(
+                AccountId::from([0x02; 32]),
+                crate::ZeitgeistAsset::ForeignAsset(1),
+                100,
+            );
+            assert_eq!(res, Err(Error::Unauthorized));
+        }
+
+        #[ink::test]
+        fn distribute_checks_ztg_balance() {
+            let fund = AccountId::from([0x01; 32]);
+            ink::env::test::set_caller::<Environment>(fund);
+            let mut contract = DividendWallet::new();
+            ink::env::test::set_account_balance::<Environment>(
+                ink::env::test::callee::<Environment>(),
+                99,
+            );
+
+            let res =
+                contract.distribute(AccountId::from([0x02; 32]), crate::ZeitgeistAsset::Ztg, 100);
+            assert_eq!(res, Err(Error::InsufficientFunds));
+        }
+
+        #[ink::test]
+        fn upgrade_rejects_other_callers() {
+            let fund = AccountId::from([0x01; 32]);
+            ink::env::test::set_caller::<Environment>(fund);
+            let mut contract = DividendWallet::new();
+
+            ink::env::test::set_caller::<Environment>(AccountId::from([0x02; 32]));
+            let res = contract.upgrade([0x03; 32].into());
+            assert_eq!(res, Err(Error::Unauthorized));
+        }
+
+        #[ink::test]
+        fn env_errors_are_mapped() {
+            use ink::env::Error as EnvError;
+
+            assert_eq!(
+                EnvFailure::from(EnvError::CallRuntimeFailed),
+                EnvFailure::CallRuntimeFailed
+            );
+            assert_eq!(
+                EnvFailure::from(EnvError::Decode("bad input".into())),
+                EnvFailure::Decode
+            );
+            assert_eq!(
+                EnvFailure::from(EnvError::CalleeTrapped),
+                EnvFailure::CalleeTrapped
+            );
+            assert_eq!(EnvFailure::from(EnvError::Unknown), EnvFailure::Unknown);
+        }
     }
 
     // TODO: write e2e tests if you have time
 }
 
+/// The errors that `pallet-contracts` can return to a contract, without their payloads so that
+/// they can be returned from messages.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, scale::Encode, scale::Decode)]
+#[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+pub enum EnvFailure {
+    /// A value could not be decoded.
+    Decode,
+    /// An error that can only occur in the off-chain environment.
+    OffChain,
+    /// The call to another contract has trapped.
+    CalleeTrapped,
+    /// The call to another contract has been reverted.
+    CalleeReverted,
+    /// The queried contract storage entry is missing.
+    KeyNotFound,
+    /// Deprecated: the balance would drop below the minimum balance.
+    BelowSubsistenceThreshold,
+    /// A transfer failed, most likely because of reserved or locked balance.
+    TransferFailed,
+    /// Deprecated: the endowment was too low.
+    EndowmentTooLow,
+    /// No code could be found at the supplied code hash.
+    CodeNotFound,
+    /// The account that was called is not a contract.
+    NotCallable,
+    /// An unknown error has occurred.
+    Unknown,
+    /// Debug message recording was disabled.
+    LoggingDisabled,
+    /// The call dispatched by `call_runtime` was executed but returned an error.
+    CallRuntimeFailed,
+    /// ECDSA public key recovery failed.
+    EcdsaRecoveryFailed,
+}
+
+impl From<ink::env::Error> for EnvFailure {
+    fn from(e: ink::env::Error) -> Self {
+        use ink::env::Error as EnvError;
+
+        match e {
+            EnvError::Decode(_) => EnvFailure::Decode,
+            EnvError::CalleeTrapped => EnvFailure::CalleeTrapped,
+            EnvError::CalleeReverted => EnvFailure::CalleeReverted,
+            EnvError::KeyNotFound => EnvFailure::KeyNotFound,
+            EnvError::_BelowSubsistenceThreshold => EnvFailure::BelowSubsistenceThreshold,
+            EnvError::TransferFailed => EnvFailure::TransferFailed,
+            EnvError::_EndowmentTooLow => EnvFailure::EndowmentTooLow,
+            EnvError::CodeNotFound => EnvFailure::CodeNotFound,
+            EnvError::NotCallable => EnvFailure::NotCallable,
+            EnvError::Unknown => EnvFailure::Unknown,
+            EnvError::LoggingDisabled => EnvFailure::LoggingDisabled,
+            EnvError::CallRuntimeFailed => EnvFailure::CallRuntimeFailed,
+            EnvError::EcdsaRecoveryFailed => EnvFailure::EcdsaRecoveryFailed,
+            // `OffChain` only exists when `ink_env` is built with `std`
+            #[allow(unreachable_patterns)]
+            _ => EnvFailure::OffChain,
+        }
+    }
+}
+
 #[derive(scale::Encode, scale::Decode)]
 pub enum RuntimeCall {
     /// This index can be found by investigating runtime configuration. You can check the
@@ -106,8 +293,8 @@ pub enum ZeitgeistAsset {
     CategoricalOutcome(u128, u16),
     ScalarOutcome, //(u128, ScalarPosition),
     CombinatorialOutcome,
-    PoolShare, //(SerdeWrapper<PoolId>),
-    Ztg,       // default
+    PoolShare(u128), //(SerdeWrapper<PoolId>),
+    Ztg,             // default
     ForeignAsset(u32),
 }
 
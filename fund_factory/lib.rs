@@ -0,0 +1,445 @@
+#![cfg_attr(not(feature = "std"), no_std, no_main)]
+
+/*
+
+The factory instantiates funds from a stored ZeitFund code hash, so that managers do not have to
+supply the DividendWallet code hash themselves. Each fund is instantiated with its id as the salt,
+and each fund instantiates its own dividend wallet, so that every fund and wallet gets a unique
+address.
+
+The factory keeps a registry of every fund that it created, by manager and by creation time.
+
+*/
+
+#[ink::contract]
+mod fund_factory {
+    use dividend_wallet::EnvFailure;
+    use ink::prelude::vec::Vec;
+    use ink::storage::Mapping;
+    use ink::ToAccountId;
+    use scale::Encode;
+    use zeit_fund::{
+        FeeConfig, ManagerVesting, OpenEndedConfig, RiskLimits, TimelockConfig, TokenMetadata,
+        ZeitFundRef,
+    };
+
+    #[ink(storage)]
+    pub struct FundFactory {
+        /// The account that can update the code hashes.
+        owner: AccountId,
+        /// The code hash that funds are instantiated from.
+        fund_code_hash: Hash,
+        /// The code hash that funds instantiate their dividend wallets from.
+        wallet_code_hash: Hash,
+        /// Every fund created, by id in order of creation.
+        funds: Mapping<u32, FundInfo>,
+        /// The number of funds created, which is also the next fund id.
+        fund_count: u32,
+        /// The ids of each manager's funds, as (manager, index) => fund id.
+        manager_funds: Mapping<(AccountId, u32), u32>,
+        /// The number of funds created by each manager.
+        manager_fund_counts: Mapping<AccountId, u32>,
+    }
+
+    /// The parameters of a new fund. The caller of `create_fund` becomes its manager.
+    #[derive(Debug, Clone, PartialEq, Eq, scale::Encode, scale::Decode)]
+    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+    pub struct FundParams {
+        pub guardian: AccountId,
+        pub metadata: TokenMetadata,
+        pub total_shares: Balance,
+        pub manager_vesting: Option<ManagerVesting>,
+        pub funding_deadline: Timestamp,
+        pub maturity: Timestamp,
+        pub fees: FeeConfig,
+        pub risk_limits: RiskLimits,
+        pub timelock: TimelockConfig,
+        pub open_ended: Option<OpenEndedConfig>,
+    }
+
+    /// A fund created by the factory.
+    #[derive(Debug, Clone, PartialEq, Eq, scale::Encode, scale::Decode)]
+    #[cfg_attr(
+        feature = "std",
+        derive(scale_info::TypeInfo, ink::storage::traits::StorageLayout)
+    )]
+    pub struct FundInfo {
+        /// The address of the fund.
+        pub fund: AccountId,
+        /// The manager that created the fund.
+        pub manager: AccountId,
+        /// The timestamp at which the fund was created.
+        pub created_at: Timestamp,
+    }
+
+    /// Event emitted when a fund is created.
+    #[ink(event)]
+    pub struct FundCreated {
+        #[ink(topic)]
+        id: u32,
+        #[ink(topic)]
+        fund: AccountId,
+        #[ink(topic)]
+        manager: AccountId,
+        created_at: Timestamp,
+    }
+
+    /// Event emitted when the owner changes the code hashes of new funds.
+    #[ink(event)]
+    pub struct CodeHashesUpdated {
+        fund_code_hash: Hash,
+        wallet_code_hash: Hash,
+    }
+
+    /// The factory error types.
+    #[derive(Debug, PartialEq, Eq, scale::Encode, scale::Decode)]
+    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+    pub enum Error {
+        /// Returned if someone other than the owner changes the code hashes.
+        Unauthorized,
+        /// Returned if instantiating the fund failed, with the reason.
+        InstantiationFailed(EnvFailure),
+    }
+
+    /// The factory result type.
+    pub type Result<T> = core::result::Result<T, Error>;
+
+    impl FundFactory {
+        /// Creates a factory for funds with the given code hashes. The caller becomes the owner.
+        #[ink(constructor)]
+        pub fn new(fund_code_hash: Hash, wallet_code_hash: Hash) -> Self {
+            Self {
+                owner: Self::env().caller(),
+                fund_code_hash,
+                wallet_code_hash,
+                funds: Default::default(),
+                fund_count: 0,
+                manager_funds: Default::default(),
+                manager_fund_counts: Default::default(),
+            }
+        }
+
+        // region: Funds
+
+        /// Instantiates a new fund with the caller as its manager and adds it to the registry.
+        ///
+        /// On success a `FundCreated` event is emitted.
+        #[ink(message)]
+        pub fn create_fund(&mut self, params: FundParams) -> Result<AccountId> {
+            let manager = self.env().caller();
+
+            // The factory is the deployer, so the fund id is a unique salt. Keep in sync with
+            // `predict_addresses`.
+            let fund = ZeitFundRef::new(
+                manager,
+                params.guardian,
+                params.metadata,
+                params.total_shares,
+                params.manager_vesting,
+                params.funding_deadline,
+                params.maturity,
+                params.fees,
+                params.risk_limits,
+                params.timelock,
+                params.open_ended,
+                self.wallet_code_hash,
+            )
+            .code_hash(self.fund_code_hash)
+            .endowment(0)
+            .salt_bytes(self.fund_count.to_le_bytes())
+            .try_instantiate()
+            .map_err(|e| Error::InstantiationFailed(e.into()))?
+            // The fund could not decode the constructor call
+            .map_err(|_| Error::InstantiationFailed(EnvFailure::Decode))?
+            .to_account_id();
+
+            self.register(fund, manager);
+
+            Ok(fund)
+        }
+
+        /// Predicts the addresses of the fund and its dividend wallet if `manager` were to call
+        /// `create_fund` with `params` next, so that front-ends can know them before deployment.
+        #[ink(message)]
+        pub fn predict_addresses(
+            &self,
+            manager: AccountId,
+            params: FundParams,
+        ) -> (AccountId, AccountId) {
+            let mut input = ink::selector_bytes!("new").to_vec();
+            (
+                manager,
+                params.guardian,
+                params.metadata,
+                params.total_shares,
+                params.manager_vesting,
+                params.funding_deadline,
+                params.maturity,
+                params.fees,
+                params.risk_limits,
+                params.timelock,
+                params.open_ended,
+                self.wallet_code_hash,
+            )
+                .encode_to(&mut input);
+
+            let fund = zeit_fund::contract_address(
+                &self.env().account_id(),
+                &self.fund_code_hash,
+                &input,
+                &self.fund_count.to_le_bytes(),
+            );
+            let wallet = zeit_fund::predict_dividend_wallet_address(&fund, &self.wallet_code_hash);
+
+            (fund, wallet)
+        }
+
+        /// Adds `fund` to the registry and returns its id.
+        fn register(&mut self, fund: AccountId, manager: AccountId) -> u32 {
+            let id = self.fund_count;
+            let created_at = self.env().block_timestamp();
+            self.funds.insert(
+                id,
+                &FundInfo {
+                    fund,
+                    manager,
+                    created_at,
+                },
+            );
+            self.fund_count += 1;
+
+            let count = self.manager_fund_counts.get(manager).unwrap_or(0);
+            self.manager_funds.insert((manager, count), &id);
+            self.manager_fund_counts.insert(manager, &(count + 1));
+
+            self.emit(FundCreated {
+                id,
+                fund,
+                manager,
+                created_at,
+            });
+
+            id
+        }
+
+        /// Returns the fund with the given id, if it exists.
+        #[ink(message)]
+        pub fn fund(&self, id: u32) -> Option<FundInfo> {
+            self.funds.get(id)
+        }
+
+        /// The number of funds created.
+        #[ink(message)]
+        pub fn fund_count(&self) -> u32 {
+            self.fund_count
+        }
+
+        /// Every fund created by `manager`, in order of creation.
+        #[ink(message)]
+        pub fn funds_by_manager(&self, manager: AccountId) -> Vec<FundInfo> {
+            let count = self.manager_fund_counts.get(manager).unwrap_or(0);
+            (0..count)
+                .filter_map(|i| self.manager_funds.get((manager, i)))
+                .filter_map(|id| self.funds.get(id))
+                .collect()
+        }
+
+        /// Every fund created between `from` and `to`, inclusive, in order of creation.
+        #[ink(message)]
+        pub fn funds_created_between(&self, from: Timestamp, to: Timestamp) -> Vec<FundInfo> {
+            // Funds are stored in order of creation, so binary search for the first one
+            let (mut low, mut high) = (0, self.fund_count);
+            while low < high {
+                let mid = low + (high - low) / 2;
+                match self.funds.get(mid) {
+                    Some(info) if info.created_at < from => low = mid + 1,
+                    _ => high = mid,
+                }
+            }
+
+            (low..self.fund_count)
+                .map_while(|id| self.funds.get(id))
+                .take_while(|info| info.created_at <= to)
+                .collect()
+        }
+
+        // endregion
+
+        // region: Code Hashes
+
+        /// Changes the code hashes that new funds are instantiated from. Only the owner can
+        /// change them.
+        #[ink(message)]
+        pub fn set_code_hashes(
+            &mut self,
+            fund_code_hash: Hash,
+            wallet_code_hash: Hash,
+        ) -> Result<()> {
+            if self.env().caller() != self.owner {
+                return Err(Error::Unauthorized);
+            }
+
+            self.fund_code_hash = fund_code_hash;
+            self.wallet_code_hash = wallet_code_hash;
+            self.emit(CodeHashesUpdated {
+                fund_code_hash,
+                wallet_code_hash,
+            });
+
+            Ok(())
+        }
+
+        /// The code hash that funds are instantiated from.
+        #[ink(message)]
+        pub fn fund_code_hash(&self) -> Hash {
+            self.fund_code_hash
+        }
+
+        /// The code hash that funds instantiate their dividend wallets from.
+        #[ink(message)]
+        pub fn wallet_code_hash(&self) -> Hash {
+            self.wallet_code_hash
+        }
+
+        /// The account that can update the code hashes.
+        #[ink(message)]
+        pub fn owner(&self) -> AccountId {
+            self.owner
+        }
+
+        // endregion
+
+        /// Emits an event of this contract. `self.env().emit_event()` cannot infer which contract
+        /// an event belongs to, since the fund and dividend wallet define events too.
+        fn emit<E>(&self, event: E)
+        where
+            E: Into<<FundFactory as ink::reflect::ContractEventBase>::Type>,
+        {
+            ink::codegen::EmitEvent::<FundFactory>::emit_event(self.env(), event);
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::{Error, FundFactory, FundInfo, FundParams};
+        use crate::fund_factory::Environment;
+        use ink::primitives::{AccountId, Hash};
+
+        fn create_factory(owner: AccountId) -> FundFactory {
+            ink::env::test::set_caller::<Environment>(owner);
+            FundFactory::new(Hash::from([0x01; 32]), Hash::from([0x02; 32]))
+        }
+
+        #[ink::test]
+        fn code_hashes_can_only_be_set_by_owner() {
+            let owner = AccountId::from([0x01; 32]);
+            let mut factory = create_factory(owner);
+            assert_eq!(factory.owner(), owner);
+            assert_eq!(factory.fund_code_hash(), Hash::from([0x01; 32]));
+            assert_eq!(factory.wallet_code_hash(), Hash::from([0x02; 32]));
+
+            ink::env::test::set_caller::<Environment>(AccountId::from([0x02; 32]));
+            assert_eq!(
+                factory.set_code_hashes(Hash::from([0x03; 32]), Hash::from([0x04; 32])),
+                Err(Error::Unauthorized)
+            );
+
+            ink::env::test::set_caller::<Environment>(owner);
+            assert_eq!(
+                factory.set_code_hashes(Hash::from([0x03; 32]), Hash::from([0x04; 32])),
+                Ok(())
+            );
+            assert_eq!(factory.fund_code_hash(), Hash::from([0x03; 32]));
+            assert_eq!(factory.wallet_code_hash(), Hash::from([0x04; 32]));
+        }
+
+        #[ink::test]
+        fn predicted_addresses_are_unique() {
+            let alice = AccountId::from([0x01; 32]);
+            let mut factory = create_factory(alice);
+            let params = FundParams {
+                guardian: AccountId::from([0x0A; 32]),
+                metadata: Default::default(),
+                total_shares: 1_000,
+                manager_vesting: Some(zeit_fund::ManagerVesting {
+                    cliff: 5,
+                    duration: 10,
+                }),
+                funding_deadline: 10,
+                maturity: 20,
+                fees: Default::default(),
+                risk_limits: zeit_fund::RiskLimits {
+                    allowed_markets: vec![1],
+                    allowed_pools: vec![1],
+                    max_market_exposure_bps: 10_000,
+                    max_total_exposure_bps: 10_000,
+                },
+                timelock: zeit_fund::TimelockConfig {
+                    threshold: 1_000,
+                    delay: 24 * 60 * 60 * 1000,
+                },
+                open_ended: Some(zeit_fund::OpenEndedConfig {
+                    nav_oracle: None,
+                    window_interval: 7 * 24 * 60 * 60 * 1000,
+                    window_length: 24 * 60 * 60 * 1000,
+                    subscription_cap: 10_000,
+                    redemption_gate_bps: 1_000,
+                }),
+            };
+
+            let (fund, wallet) = factory.predict_addresses(alice, params.clone());
+            assert_ne!(fund, wallet);
+            assert_eq!(
+                wallet,
+                zeit_fund::predict_dividend_wallet_address(&fund, &factory.wallet_code_hash())
+            );
+
+            // A different manager or the next fund id gives different addresses
+            let (other_fund, other_wallet) =
+                factory.predict_addresses(AccountId::from([0x02; 32]), params.clone());
+            assert_ne!(fund, other_fund);
+            assert_ne!(wallet, other_wallet);
+            factory.register(fund, alice);
+            assert_ne!(factory.predict_addresses(alice, params).0, fund);
+        }
+
+        #[ink::test]
+        fn registry_works() {
+            let alice = AccountId::from([0x01; 32]);
+            let bob = AccountId::from([0x02; 32]);
+            let mut factory = create_factory(alice);
+            let info = |fund: u8, manager, created_at| FundInfo {
+                fund: AccountId::from([fund; 32]),
+                manager,
+                created_at,
+            };
+
+            for (fund, manager, created_at) in
+                [(0xA0, alice, 10), (0xA1, bob, 20), (0xA2, alice, 20)]
+            {
+                ink::env::test::set_block_timestamp::<Environment>(created_at);
+                factory.register(AccountId::from([fund; 32]), manager);
+            }
+
+            assert_eq!(factory.fund_count(), 3);
+            assert_eq!(factory.fund(1), Some(info(0xA1, bob, 20)));
+            assert_eq!(factory.fund(3), None);
+            assert_eq!(
+                factory.funds_by_manager(alice),
+                [info(0xA0, alice, 10), info(0xA2, alice, 20)]
+            );
+            assert_eq!(factory.funds_by_manager(bob), [info(0xA1, bob, 20)]);
+            assert_eq!(factory.funds_by_manager(AccountId::from([0x03; 32])), []);
+
+            assert_eq!(
+                factory.funds_created_between(15, 20),
+                [info(0xA1, bob, 20), info(0xA2, alice, 20)]
+            );
+            assert_eq!(
+                factory.funds_created_between(0, 10),
+                [info(0xA0, alice, 10)]
+            );
+            assert_eq!(factory.funds_created_between(21, 100), []);
+        }
+    }
+}
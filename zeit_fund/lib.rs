@@ -1,8 +1,15 @@
 #![cfg_attr(not(feature = "std"), no_std, no_main)]
 
-use ink::primitives::AccountId;
+use ink::prelude::{string::String, vec::Vec};
+use ink::primitives::{AccountId, Hash};
 use sp_runtime::MultiAddress;
 
+// Export ZeitFund so that it can be used in fund_factory
+pub use self::zeit_fund::{
+    FeeConfig, ManagerVesting, OpenEndedConfig, RiskLimits, TimelockConfig, TokenMetadata,
+    ZeitFundRef,
+};
+
 /*
 
 Workflow:
@@ -12,13 +19,19 @@ Workflow:
         Otherwise, there is nothing stopping the manager from dumping. By locking, their
         liquidity is locked until liquidation of the fund.
 3. Manager can interact with markets & issue dividends of ZTG.
+    3a. If the funding goal is not reached before the funding deadline, users can get their ZTG
+        back with refund().
 
 
+4. After maturity, the manager liquidates the fund. If the manager does not do so within the
+   grace period, anyone can. Users then redeem their shares for ZTG with redeem().
 
 NOTE:
-No dynamic insert of funds. There is a period where funds are added and afterwards no more.
-Users cannot force liquidation.
-Users that wish to exit can only resell the ERC20 token, not liquidate for the individual market positions.
+No dynamic insert of funds, unless the fund is open-ended. Otherwise, there is a period where funds
+are added and afterwards no more.
+Users cannot force liquidation before maturity.
+Users that wish to exit can resell the PSP22 share token, or redeem_in_kind() for their slice of the
+individual market positions.
 
 NOTE:
 self.env().block_number() is broken for some reason. Fortunately self.env().block_timestamp() works.
@@ -31,18 +44,67 @@ TODO: check to see if env().block_number() works on substrate contracts node & m
 
 #[ink::contract]
 mod zeit_fund {
-    use crate::{AssetManagerCall, PredictionMarketsCall, RuntimeCall, SwapsCall, ZeitgeistAsset};
-    use dividend_wallet::DividendWalletRef;
+    use crate::{
+        AssetManagerCall, LegacyZeitgeistAsset, PSP22Error, PSP22Metadata, PredictionMarketsCall,
+        RuntimeCall, SwapsCall, ZeitgeistAsset, PSP22,
+    };
+    use dividend_wallet::{DividendWalletRef, EnvFailure, Error as WalletError};
+    use ink::codegen::TraitCallBuilder;
     use ink::env::call::FromAccountId;
     use ink::env::Error as EnvError;
-    use ink::prelude::vec::Vec;
-    use ink::storage::Mapping;
+    use ink::prelude::{string::String, vec::Vec};
+    use ink::storage::traits::StorageKey;
+    use ink::storage::{Lazy, Mapping};
     use ink::ToAccountId;
 
+    /// The time after maturity during which only the manager can liquidate the fund (7 days).
+    pub const LIQUIDATION_GRACE_PERIOD: Timestamp = 7 * 24 * 60 * 60 * 1000;
+
+    /// Scales `dividends_per_share` so that dividends are not lost to rounding.
+    pub const DIVIDEND_PRECISION: Balance = 1_000_000_000_000;
+
+    /// The denominator of fees given in basis points.
+    pub const BASIS_POINTS: Balance = 10_000;
+
+    /// The length of a year, which management fees are annualised over.
+    pub const YEAR: Timestamp = 365 * 24 * 60 * 60 * 1000;
+
+    /// Scales NAV per share values, so that a NAV per share of 1 ZTG is `NAV_PRECISION`.
+    pub const NAV_PRECISION: Balance = 1_000_000_000_000;
+
+    /// A manager proposal passes once votes for it hold more than this percentage of the shares.
+    pub const MANAGER_VOTE_QUORUM_PERCENT: Balance = 50;
+
+    /// The time after a manager proposal reaches quorum before it can be executed (2 days).
+    pub const MANAGER_VOTE_TIMELOCK: Timestamp = 2 * 24 * 60 * 60 * 1000;
+
+    /// A queued manager action is vetoed once vetoes against it hold more than this percentage of
+    /// the shares.
+    pub const ACTION_VETO_PERCENT: Balance = 33;
+
+    /// Shares are minted 1:1 with the ZTG funded, so they have the same decimals as ZTG.
+    pub const SHARE_DECIMALS: u8 = 10;
+
+    /// The storage version of this code. `migrate()` brings funds with an older version up to it
+    /// after an upgrade.
+    pub const STORAGE_VERSION: u32 = 5;
+
+    /// A balance at a timestamp.
+    pub type Checkpoint = (Timestamp, Balance);
+
+    /// `a * b / c`, or `Error::Overflow` if the product doesn't fit in a `Balance`.
+    fn mul_div(a: Balance, b: Balance, c: Balance) -> Result<Balance> {
+        a.checked_mul(b)
+            .and_then(|product| product.checked_div(c))
+            .ok_or(Error::Overflow)
+    }
+
     #[ink(storage)]
     pub struct ZeitFund {
         /// Stores a single `bool` value on the storage.
         manager: AccountId,
+        /// The name and symbol of the share token.
+        metadata: TokenMetadata,
         /// Total token supply.
         total_supply: Balance,
         /// Mapping from owner to number of owned token.
@@ -52,17 +114,595 @@ mod zeit_fund {
         allowances: Mapping<(AccountId, AccountId), Balance>,
         /// The amount of ZTG that the fund has received already.
         funding_amount: Balance,
-        /// Locks the manager's shares so that they can't be transferred.
+        /// The amount of ZTG that the fund must receive to start.
+        funding_goal: Balance,
+        /// The timestamp after which the fund no longer accepts funding. If the fund is not
+        /// completely funded by then, users can get refunds.
+        funding_deadline: Timestamp,
+        /// The timestamp after which the fund can be liquidated.
+        maturity: Timestamp,
+        /// The stored lifecycle state of the fund. See `state()` for the effective state.
+        state: FundState,
+        /// The account that can pause the fund in an emergency.
+        guardian: AccountId,
+        /// The state that the fund returns to when it is unpaused.
+        paused_from: FundState,
+        /// Locked the manager's shares under storage version 3 and earlier. `migrate()` turns it
+        /// into a `manager_vesting` schedule, after which this stays false.
         lock_manager_shares: bool,
+        /// Locks the manager's shares until they vest, so that they can't be transferred.
+        manager_vesting: Lazy<ManagerVesting>,
+        /// The shares that the manager held at activation, which vest on `manager_vesting`.
+        manager_vesting_shares: Lazy<Balance>,
+        /// The account whose shares vest on `manager_vesting`. It follows the manager's key on a
+        /// rotation, but stays with a manager that shareholders replace.
+        manager_vesting_account: Lazy<AccountId>,
         /// The wallet that dividends are issued to so that they can no longer be used
         /// by the manager.
         dividend_wallet: DividendWalletRef,
-        /// An array of dividends being issued at certain blocks.
-        dividends: Vec<(Timestamp, Balance)>,
+        /// Every asset that was issued as a dividend under storage version 1, in that version's
+        /// encoding. `migrate()` moves them into `dividend_asset_list`, after which this stays
+        /// empty.
+        dividend_assets: Vec<LegacyZeitgeistAsset>,
+        /// The number of assets that have been issued as a dividend.
+        dividend_asset_count: Lazy<u32>,
+        /// Every asset that has been issued as a dividend, by index.
+        dividend_asset_list: Mapping<u32, ZeitgeistAsset>,
+        /// The sum of every dividend issued per share for each asset, scaled by
+        /// `DIVIDEND_PRECISION`.
+        dividends_per_share: Mapping<ZeitgeistAsset, Balance>,
+        /// The value of `dividends_per_share` when each account's dividend was last settled.
+        dividend_debt: Mapping<(AccountId, ZeitgeistAsset), Balance>,
+        /// Dividends that each account has earned but not yet claimed, as of its last
+        /// settlement.
+        dividend_credit: Mapping<(AccountId, ZeitgeistAsset), Balance>,
         /// The last time that a user claimed a dividend.
         last_claimed_dividend: Mapping<AccountId, Timestamp>,
+        /// The number of balance checkpoints that each account has.
+        checkpoint_counts: Mapping<AccountId, u32>,
+        /// The balance checkpoints of each account, as (account, index) => (timestamp, balance).
+        checkpoints: Mapping<(AccountId, u32), Checkpoint>,
+        /// The number of total supply checkpoints.
+        supply_checkpoint_count: u32,
+        /// The total supply checkpoints, as index => (timestamp, total supply).
+        supply_checkpoints: Mapping<u32, Checkpoint>,
+        /// Proposals to replace the manager, by id.
+        manager_proposals: Mapping<u32, ManagerProposal>,
+        /// The number of manager proposals made, which is also the next proposal id.
+        manager_proposal_count: u32,
+        /// The accounts that have voted on each manager proposal.
+        manager_votes: Mapping<(u32, AccountId), ()>,
+        /// The fees that the fund charges.
+        fees: FeeConfig,
+        /// The last net asset value of the fund in ZTG reported by the manager.
+        nav: Balance,
+        /// The highest NAV per share that performance fees have been charged against, scaled
+        /// by `NAV_PRECISION`.
+        high_water_mark: Balance,
+        /// The last time that management fees were accrued.
+        management_fee_accrued_at: Timestamp,
+        /// The last time that performance fees were crystallised.
+        performance_fee_crystallised_at: Timestamp,
+        /// Every position that the fund held under storage version 2, in that version's
+        /// encoding. `migrate()` moves them into `position_list`, after which this stays empty.
+        positions: Vec<LegacyPosition>,
+        /// The number of positions that the fund holds.
+        position_count: Lazy<u32>,
+        /// Every market, pool and outcome asset position that the fund holds, by index.
+        position_list: Mapping<u32, Position>,
+        /// The index of each position in `position_list`.
+        position_index: Mapping<Position, u32>,
+        /// The pool shares that the fund holds in each pool.
+        pool_shares: Mapping<u128, Balance>,
+        /// The outcome assets that the fund holds, as far as its calls tell.
+        asset_holdings: Mapping<ZeitgeistAsset, Balance>,
+        /// The complete sets that the fund holds in each market, as far as its calls tell.
+        complete_sets: Mapping<u128, Balance>,
+        /// The mandate that the manager's trades must stay within.
+        risk_limits: RiskLimits,
+        /// The ZTG that the fund has put into each market.
+        market_exposure: Mapping<u128, Balance>,
+        /// The ZTG that the fund has put into each pool.
+        pool_exposure: Mapping<u128, Balance>,
+        /// The ZTG that the fund has put into markets and pools in total.
+        total_exposure: Balance,
+        /// The terms of the timelock on large manager actions.
+        timelock: Lazy<TimelockConfig>,
+        /// Manager actions queued behind the timelock, by id.
+        queued_actions: Mapping<u32, QueuedAction>,
+        /// The number of actions queued, which is also the next action id.
+        queued_action_count: Lazy<u32>,
+        /// The accounts that have vetoed each queued action.
+        action_vetoes: Mapping<(u32, AccountId), ()>,
+        /// The dealing terms if the fund is open-ended.
+        open_ended: Lazy<OpenEndedConfig>,
+        /// The time at which the fund was completely funded.
+        activated_at: Lazy<Timestamp>,
+        /// Subscriptions waiting for the next NAV strike, as index => (user, ZTG).
+        subscription_queue: Mapping<u32, (AccountId, Balance)>,
+        /// The index of the first queued subscription.
+        subscription_queue_start: Lazy<u32>,
+        /// The index that the next subscription is queued at.
+        subscription_queue_end: Lazy<u32>,
+        /// The ZTG of queued subscriptions, which is held by the fund but not part of its NAV.
+        pending_subscriptions: Lazy<Balance>,
+        /// The ZTG subscribed in the latest dealing window, as (window, ZTG).
+        window_subscriptions: Lazy<(u64, Balance)>,
+        /// Redemptions waiting for the next NAV strike, as index => (user, shares).
+        redemption_queue: Mapping<u32, (AccountId, Balance)>,
+        /// The index of the first queued redemption.
+        redemption_queue_start: Lazy<u32>,
+        /// The index that the next redemption is queued at.
+        redemption_queue_end: Lazy<u32>,
+        /// The shares of queued redemptions, which the fund holds until they are settled.
+        pending_redemptions: Lazy<Balance>,
+        /// The number of members of the manager committee. While there are any, manager calls
+        /// need the approval of `committee_threshold` members.
+        committee_size: Lazy<u32>,
+        /// The members of the manager committee, by index.
+        committee_members: Mapping<u32, AccountId>,
+        /// The number of member approvals that committee proposals need.
+        committee_threshold: Lazy<u32>,
+        /// Committee proposals, by id.
+        committee_proposals: Mapping<u32, CommitteeProposal>,
+        /// The number of committee proposals made, which is also the next proposal id.
+        committee_proposal_count: Lazy<u32>,
+        /// The members that have approved each committee proposal.
+        committee_approvals: Mapping<(u32, AccountId), ()>,
+        /// The roles granted to accounts other than the manager and the guardian.
+        roles: Mapping<(Role, AccountId), ()>,
+        /// The account that the manager proposed to hand its role to, until it accepts.
+        pending_manager: Lazy<Option<AccountId>>,
+        /// The layout version of this storage. Funds without one have version 1.
+        ///
+        /// Code that a fund is upgraded to must be able to decode the storage of the current
+        /// version, so new versions only add `Lazy` and `Mapping` fields, which are not stored
+        /// with the rest of the struct. `migrate()` then moves data into them.
+        storage_version: Lazy<u32>,
+    }
+
+    /// A position that the fund holds, recorded from the manager's runtime calls.
+    #[derive(scale::Encode, scale::Decode, Clone, PartialEq)]
+    #[cfg_attr(
+        feature = "std",
+        derive(Debug, scale_info::TypeInfo, ink::storage::traits::StorageLayout)
+    )]
+    pub enum Position {
+        /// A market that the fund bought complete sets or outcome assets of.
+        Market(u128),
+        /// A pool that the fund provides liquidity to.
+        Pool(u128),
+        /// An outcome asset that the fund swapped into.
+        Asset(ZeitgeistAsset),
+    }
+
+    /// `Position` as funds stored it under storage version 2 and earlier. See
+    /// `LegacyZeitgeistAsset`.
+    #[derive(scale::Encode, scale::Decode, Clone, PartialEq)]
+    #[cfg_attr(
+        feature = "std",
+        derive(Debug, scale_info::TypeInfo, ink::storage::traits::StorageLayout)
+    )]
+    pub enum LegacyPosition {
+        Market(u128),
+        Pool(u128),
+        Asset(LegacyZeitgeistAsset),
+    }
+
+    impl LegacyPosition {
+        /// The position in the current encoding, or none for a pool share asset.
+        pub fn upgrade(self) -> Option<Position> {
+            match self {
+                LegacyPosition::Market(market_id) => Some(Position::Market(market_id)),
+                LegacyPosition::Pool(pool_id) => Some(Position::Pool(pool_id)),
+                LegacyPosition::Asset(asset) => asset.upgrade().map(Position::Asset),
+            }
+        }
+    }
+
+    /// The name and symbol of a fund's share token.
+    #[derive(Debug, Default, Clone, PartialEq, Eq, scale::Encode, scale::Decode)]
+    #[cfg_attr(
+        feature = "std",
+        derive(scale_info::TypeInfo, ink::storage::traits::StorageLayout)
+    )]
+    pub struct TokenMetadata {
+        pub name: Option<String>,
+        pub symbol: Option<String>,
+    }
+
+    /// The fees that a fund charges by minting new shares to a fee recipient.
+    #[derive(Debug, Clone, PartialEq, Eq, scale::Encode, scale::Decode)]
+    #[cfg_attr(
+        feature = "std",
+        derive(scale_info::TypeInfo, ink::storage::traits::StorageLayout)
+    )]
+    pub struct FeeConfig {
+        /// The account that fee shares are minted to.
+        pub recipient: AccountId,
+        /// The yearly management fee in basis points of the outstanding shares.
+        pub management_fee_bps: u16,
+        /// The performance fee in basis points of the gain above the high-water mark.
+        pub performance_fee_bps: u16,
+        /// The minimum time between performance fee crystallisations.
+        pub crystallisation_period: Timestamp,
+    }
+
+    impl RiskLimits {
+        /// True if the limits allow no more than 100% exposure.
+        pub fn is_valid(&self) -> bool {
+            Balance::from(self.max_market_exposure_bps) <= BASIS_POINTS
+                && Balance::from(self.max_total_exposure_bps) <= BASIS_POINTS
+        }
+    }
+
+    impl OpenEndedConfig {
+        /// True if dealing windows fit within their interval and the gate is at most 100%.
+        pub fn is_valid(&self) -> bool {
+            self.window_interval > 0
+                && self.window_length <= self.window_interval
+                && Balance::from(self.redemption_gate_bps) <= BASIS_POINTS
+        }
+    }
+
+    impl ManagerVesting {
+        /// True if the cliff is within the vesting duration.
+        pub fn is_valid(&self) -> bool {
+            self.duration > 0 && self.cliff <= self.duration
+        }
+
+        /// The part of `shares` that is still locked `elapsed` after activation.
+        pub fn locked(&self, shares: Balance, elapsed: Timestamp) -> Balance {
+            if elapsed < self.cliff {
+                shares
+            } else if elapsed >= self.duration {
+                0
+            } else {
+                let unlocked = shares.saturating_mul(elapsed.into()) / Balance::from(self.duration);
+                shares - unlocked
+            }
+        }
+    }
+
+    impl Default for TimelockConfig {
+        /// No timelock, so that every manager action is executed right away.
+        fn default() -> Self {
+            Self {
+                threshold: Balance::MAX,
+                delay: 0,
+            }
+        }
+    }
+
+    impl Default for FeeConfig {
+        /// A fund without any fees.
+        fn default() -> Self {
+            Self {
+                recipient: AccountId::from([0; 32]),
+                management_fee_bps: 0,
+                performance_fee_bps: 0,
+                crystallisation_period: 0,
+            }
+        }
+    }
+
+    /// The mandate that a manager's trades must stay within. Exposures are given in basis points
+    /// of the fund's size, which is its last reported NAV, or its outstanding shares before
+    /// any NAV is reported.
+    #[derive(Debug, Clone, PartialEq, Eq, scale::Encode, scale::Decode)]
+    #[cfg_attr(
+        feature = "std",
+        derive(scale_info::TypeInfo, ink::storage::traits::StorageLayout)
+    )]
+    pub struct RiskLimits {
+        /// The markets that the manager can buy into.
+        pub allowed_markets: Vec<u128>,
+        /// The pools that the manager can join and swap in.
+        pub allowed_pools: Vec<u128>,
+        /// The maximum ZTG that can be put into a single market.
+        pub max_market_exposure_bps: u16,
+        /// The maximum ZTG that can be put into markets and pools in total.
+        pub max_total_exposure_bps: u16,
+    }
+
+    /// The terms on which large manager actions wait for shareholders to veto them.
+    #[derive(Debug, Clone, PartialEq, Eq, scale::Encode, scale::Decode)]
+    #[cfg_attr(
+        feature = "std",
+        derive(scale_info::TypeInfo, ink::storage::traits::StorageLayout)
+    )]
+    pub struct TimelockConfig {
+        /// Manager actions that can move more ZTG than this are queued instead of executed.
+        pub threshold: Balance,
+        /// The time that queued actions wait before they can be executed.
+        pub delay: Timestamp,
+    }
+
+    /// The schedule on which the manager's shares unlock, measured from the fund's activation.
+    /// None of the shares unlock before the cliff. Afterwards they unlock linearly, as if from
+    /// activation, until all of them are unlocked at the end of `duration`.
+    #[derive(Debug, Clone, PartialEq, Eq, scale::Encode, scale::Decode)]
+    #[cfg_attr(
+        feature = "std",
+        derive(scale_info::TypeInfo, ink::storage::traits::StorageLayout)
+    )]
+    pub struct ManagerVesting {
+        /// The time after activation before which no shares unlock.
+        pub cliff: Timestamp,
+        /// The time after activation at which every share is unlocked.
+        pub duration: Timestamp,
+    }
+
+    /// The dealing terms of an open-ended fund, which keeps issuing and redeeming shares at its
+    /// NAV after activation.
+    #[derive(Debug, Clone, PartialEq, Eq, scale::Encode, scale::Decode)]
+    #[cfg_attr(
+        feature = "std",
+        derive(scale_info::TypeInfo, ink::storage::traits::StorageLayout)
+    )]
+    pub struct OpenEndedConfig {
+        /// An account that can report the NAV besides the manager, such as an oracle contract.
+        pub nav_oracle: Option<AccountId>,
+        /// The time between the starts of two dealing windows, counted from activation.
+        pub window_interval: Timestamp,
+        /// How long each dealing window accepts requests.
+        pub window_length: Timestamp,
+        /// The most ZTG that can be subscribed in a single dealing window.
+        pub subscription_cap: Balance,
+        /// The most shares that can be redeemed at a single NAV strike, in basis points of the
+        /// outstanding shares.
+        pub redemption_gate_bps: u16,
+    }
+
+    /// An action that shareholders can vote on.
+    #[derive(Debug, Clone, PartialEq, Eq, scale::Encode, scale::Decode)]
+    #[cfg_attr(
+        feature = "std",
+        derive(scale_info::TypeInfo, ink::storage::traits::StorageLayout)
+    )]
+    pub enum ProposalAction {
+        /// Replaces the manager with the given account.
+        ReplaceManager(AccountId),
+        /// Replaces the fund's risk limits.
+        SetRiskLimits(RiskLimits),
+        /// Replaces the fund's code with the code at the given hash.
+        Upgrade(Hash),
+        /// Replaces the dividend wallet's code with the code at the given hash.
+        UpgradeDividendWallet(Hash),
+        /// Replaces the fund's timelock terms.
+        SetTimelock(TimelockConfig),
+    }
+
+    /// A shareholder proposal to replace the manager, change the fund's mandate or upgrade it.
+    #[derive(Debug, Clone, PartialEq, Eq, scale::Encode, scale::Decode)]
+    #[cfg_attr(
+        feature = "std",
+        derive(scale_info::TypeInfo, ink::storage::traits::StorageLayout)
+    )]
+    pub struct ManagerProposal {
+        /// The account that made the proposal.
+        pub proposer: AccountId,
+        /// The action that is executed if the proposal passes.
+        pub action: ProposalAction,
+        /// Votes are weighted by share balances at this timestamp.
+        pub snapshot: Timestamp,
+        /// The sum of the shares that voted for the proposal.
+        pub votes: Balance,
+        /// The timestamp at which the proposal reached quorum, if it has.
+        pub quorum_reached_at: Option<Timestamp>,
+        /// True once the proposal has been executed.
+        pub executed: bool,
+    }
+
+    /// A manager call that can be queued behind the timelock.
+    #[derive(scale::Encode, scale::Decode, Clone, PartialEq)]
+    #[cfg_attr(
+        feature = "std",
+        derive(Debug, scale_info::TypeInfo, ink::storage::traits::StorageLayout)
+    )]
+    pub enum ManagerCall {
+        /// A call into the Swaps pallet.
+        Swap(SwapsCall),
+        /// A call into the PredictionMarkets pallet.
+        PredictionMarket(PredictionMarketsCall),
+        /// A dividend of an amount of an asset.
+        IssueDividend(ZeitgeistAsset, Balance),
+    }
+
+    impl ManagerCall {
+        /// The role that is needed to send the call.
+        pub fn role(&self) -> Role {
+            match self {
+                ManagerCall::Swap(_) | ManagerCall::PredictionMarket(_) => Role::Trader,
+                ManagerCall::IssueDividend(..) => Role::DividendIssuer,
+            }
+        }
+    }
+
+    /// An action that the manager committee can approve.
+    #[derive(scale::Encode, scale::Decode, Clone, PartialEq)]
+    #[cfg_attr(
+        feature = "std",
+        derive(Debug, scale_info::TypeInfo, ink::storage::traits::StorageLayout)
+    )]
+    pub enum CommitteeAction {
+        /// Sends a manager call, which may still be queued behind the timelock.
+        Call(ManagerCall),
+        /// Adds a member to the committee.
+        AddMember(AccountId),
+        /// Removes a member from the committee.
+        RemoveMember(AccountId),
+        /// Changes the number of approvals that actions need.
+        SetThreshold(u32),
+    }
+
+    /// A proposal of a committee member that is executed once enough members approve it.
+    #[derive(scale::Encode, scale::Decode, Clone, PartialEq)]
+    #[cfg_attr(
+        feature = "std",
+        derive(Debug, scale_info::TypeInfo, ink::storage::traits::StorageLayout)
+    )]
+    pub struct CommitteeProposal {
+        /// The member that made the proposal.
+        pub proposer: AccountId,
+        /// The action that is executed once the proposal is approved.
+        pub action: CommitteeAction,
+        /// True once the proposal has been executed.
+        pub executed: bool,
+    }
+
+    /// The status of a queued manager action.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, scale::Encode, scale::Decode)]
+    #[cfg_attr(
+        feature = "std",
+        derive(scale_info::TypeInfo, ink::storage::traits::StorageLayout)
+    )]
+    pub enum ActionStatus {
+        /// Waiting for its timelock, or for the manager to execute it.
+        Queued,
+        /// Executed by the manager.
+        Executed,
+        /// Cancelled by a shareholder veto.
+        Vetoed,
+    }
+
+    /// A manager action that moves more ZTG than the timelock threshold.
+    #[derive(scale::Encode, scale::Decode, Clone, PartialEq)]
+    #[cfg_attr(
+        feature = "std",
+        derive(Debug, scale_info::TypeInfo, ink::storage::traits::StorageLayout)
+    )]
+    pub struct QueuedAction {
+        /// The call that is sent when the action is executed.
+        pub call: ManagerCall,
+        /// The most ZTG that the call can move.
+        pub ztg: Balance,
+        /// The earliest time at which the action can be executed.
+        pub eta: Timestamp,
+        /// Vetoes are weighted by share balances at this timestamp.
+        pub snapshot: Timestamp,
+        /// The sum of the shares that vetoed the action.
+        pub vetoes: Balance,
+        /// Whether the action is still queued.
+        pub status: ActionStatus,
+    }
+
+    // region: Lifecycle
+
+    /// The lifecycle states of a fund.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, scale::Encode, scale::Decode)]
+    #[cfg_attr(
+        feature = "std",
+        derive(scale_info::TypeInfo, ink::storage::traits::StorageLayout)
+    )]
+    pub enum FundState {
+        /// Users can fund the contract until the funding goal is reached.
+        Fundraising,
+        /// The funding goal was reached and the manager can interact with markets.
+        Active,
+        /// The guardian has temporarily halted the fund.
+        Paused,
+        /// The fund is winding down. Only exits and redemptions are allowed.
+        Liquidating,
+        /// Every share has been redeemed.
+        Closed,
+        /// The funding goal was not reached before the funding deadline.
+        Failed,
+    }
+
+    /// The roles that the manager can delegate. The manager holds every role but `Guardian`,
+    /// which the guardian holds.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, scale::Encode, scale::Decode)]
+    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+    pub enum Role {
+        /// Grants and revokes every role but `Guardian`.
+        Admin,
+        /// Sends swaps and prediction market calls.
+        Trader,
+        /// Issues dividends.
+        DividendIssuer,
+        /// Pauses and unpauses the fund, and grants and revokes `Guardian`.
+        Guardian,
+    }
+
+    impl Role {
+        /// The role that can grant and revoke this role.
+        pub fn admin(&self) -> Role {
+            match self {
+                Role::Guardian => Role::Guardian,
+                _ => Role::Admin,
+            }
+        }
+    }
+
+    /// The state-gated messages of a fund.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, scale::Encode, scale::Decode)]
+    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+    pub enum Action {
+        Fund,
+        Refund,
+        SwapCall,
+        PredictionMarketCall,
+        IssueDividend,
+        Claim,
+        Transfer,
+        Pause,
+        Unpause,
+        Liquidate,
+        Redeem,
+        Govern,
+        Fees,
+        Deal,
+        RedeemInKind,
+        RotateManager,
     }
 
+    impl FundState {
+        /// True if `action` can be performed while the fund is in this state.
+        pub fn allows(&self, action: Action) -> bool {
+            use Action::*;
+            match self {
+                FundState::Fundraising => {
+                    matches!(action, Fund | Claim | Transfer | Pause | RotateManager)
+                }
+                FundState::Active => matches!(
+                    action,
+                    SwapCall
+                        | PredictionMarketCall
+                        | IssueDividend
+                        | Claim
+                        | Transfer
+                        | Pause
+                        | Liquidate
+                        | Govern
+                        | Fees
+                        | Deal
+                        | RedeemInKind
+                        | RotateManager
+                ),
+                FundState::Paused => {
+                    matches!(action, Claim | Unpause | Govern | Fees | RotateManager)
+                }
+                FundState::Liquidating => matches!(
+                    action,
+                    SwapCall
+                        | PredictionMarketCall
+                        | Claim
+                        | Transfer
+                        | Pause
+                        | Redeem
+                        | RedeemInKind
+                        | Govern
+                        | RotateManager
+                ),
+                FundState::Closed => matches!(action, Claim),
+                FundState::Failed => matches!(action, Refund),
+            }
+        }
+    }
+
+    // endregion
+
     // region: Events & Errors
 
     /// Event emitted when a token transfer occurs.
@@ -89,6 +729,7 @@ mod zeit_fund {
     /// Event emitted when the manager issues a dividend.
     #[ink(event)]
     pub struct DividendIssued {
+        asset: ZeitgeistAsset,
         amount: Balance,
         timestamp: Timestamp,
     }
@@ -97,216 +738,705 @@ mod zeit_fund {
     pub struct DividendClaimed {
         #[ink(topic)]
         user: AccountId,
+        asset: ZeitgeistAsset,
         amount: Balance,
         timestamp: Timestamp,
     }
 
-    /// The ERC-20 error types.
-    #[derive(Debug, PartialEq, Eq, scale::Encode, scale::Decode)]
-    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
-    pub enum Error {
-        /// Returned if not enough balance to fulfill a request is available.
-        InsufficientBalance,
-        /// Returned if not enough allowance to fulfill a request is available.
-        InsufficientAllowance,
-        /// Returned if only the manager is allowed to call the function.
-        OnlyManagerAllowed,
-        MustBeFunded,
-        FundingTooMuch,
-        ManagerSharesAreLocked,
-        CallRuntimeFailed,
-        DividendDistributionError,
+    /// Event emitted when the lifecycle state of the fund changes.
+    #[ink(event)]
+    pub struct StateChanged {
+        from: FundState,
+        to: FundState,
     }
 
-    impl From<EnvError> for Error {
-        fn from(e: EnvError) -> Self {
-            match e {
-                EnvError::CallRuntimeFailed => Error::CallRuntimeFailed,
-                _ => panic!("Unexpected error from `pallet-contracts`."),
-            }
-        }
+    /// Event emitted when the guardian pauses the fund.
+    #[ink(event)]
+    pub struct Paused {
+        #[ink(topic)]
+        guardian: AccountId,
+        from: FundState,
     }
 
-    /// The ERC-20 result type.
-    pub type Result<T> = core::result::Result<T, Error>;
+    /// Event emitted when the guardian unpauses the fund.
+    #[ink(event)]
+    pub struct Unpaused {
+        #[ink(topic)]
+        guardian: AccountId,
+        to: FundState,
+    }
 
-    // endregion
+    /// Event emitted when a role is granted to an account.
+    #[ink(event)]
+    pub struct RoleGranted {
+        #[ink(topic)]
+        role: Role,
+        #[ink(topic)]
+        account: AccountId,
+        sender: AccountId,
+    }
 
-    impl ZeitFund {
-        /// Constructor that initializes the `bool` value to the given `init_value`.
-        #[ink(constructor)]
-        pub fn new(
-            manager: AccountId,
-            total_shares: Balance,
-            lock_manager_shares: bool,
-            dividend_wallet_hash: Hash,
-        ) -> Self {
-            // Give the zero address itself the total supply, to be distributed later
-            let mut balances = Mapping::default();
-            balances.insert(AccountId::from([0; 32]), &total_shares);
+    /// Event emitted when a role is revoked from an account.
+    #[ink(event)]
+    pub struct RoleRevoked {
+        #[ink(topic)]
+        role: Role,
+        #[ink(topic)]
+        account: AccountId,
+        sender: AccountId,
+    }
 
-            // Constructs wallet
-            let dividend_wallet = DividendWalletRef::new()
-                .code_hash(dividend_wallet_hash)
-                .endowment(0)
-                .salt_bytes([0xDE, 0xAD, 0xBE, 0xEF])
-                .instantiate();
+    /// Event emitted when a shareholder proposes a new manager or risk limits.
+    #[ink(event)]
+    pub struct ManagerProposed {
+        #[ink(topic)]
+        id: u32,
+        #[ink(topic)]
+        proposer: AccountId,
+        action: ProposalAction,
+        snapshot: Timestamp,
+    }
 
-            Self {
-                manager,
-                total_supply: total_shares,
-                balances,
-                allowances: Default::default(),
-                funding_amount: 0,
-                lock_manager_shares,
-                dividend_wallet,
-                dividends: Vec::new(),
-                last_claimed_dividend: Default::default(),
-            }
-        }
+    /// Event emitted when a shareholder votes for a manager proposal.
+    #[ink(event)]
+    pub struct ManagerVoteCast {
+        #[ink(topic)]
+        id: u32,
+        #[ink(topic)]
+        voter: AccountId,
+        weight: Balance,
+    }
 
-        /// Constructor that takes in a dividend wallet instead of creating its own.
-        ///
-        /// The dividend wallet must implement the `distribute(dest: AccountId, amount: u128)`
-        /// function.
-        #[ink(constructor)]
-        pub fn no_instantiation(
-            manager: AccountId,
-            total_shares: Balance,
-            lock_manager_shares: bool,
-            dividend_wallet: AccountId,
-        ) -> Self {
-            // Give the zero address itself the total supply, to be distributed later
-            let mut balances = Mapping::default();
-            balances.insert(AccountId::from([0; 32]), &total_shares);
+    /// Event emitted when a manager proposal is executed.
+    #[ink(event)]
+    pub struct ManagerReplaced {
+        #[ink(topic)]
+        id: u32,
+        old_manager: AccountId,
+        new_manager: AccountId,
+    }
 
-            Self {
-                manager,
-                total_supply: total_shares,
-                balances,
-                allowances: Default::default(),
-                funding_amount: 0,
-                lock_manager_shares,
-                dividend_wallet: DividendWalletRef::from_account_id(dividend_wallet),
-                dividends: Vec::new(),
-                last_claimed_dividend: Default::default(),
-            }
-        }
+    /// Event emitted when the manager proposes to hand its role to another account.
+    #[ink(event)]
+    pub struct ManagerRotationProposed {
+        #[ink(topic)]
+        manager: AccountId,
+        #[ink(topic)]
+        pending_manager: AccountId,
+    }
 
-        // TODO: separate impl of ERC20 trait
-        // region: ERC-20
+    /// Event emitted when the proposed account accepts the manager role.
+    #[ink(event)]
+    pub struct ManagerRotated {
+        old_manager: AccountId,
+        new_manager: AccountId,
+        shares: Balance,
+    }
 
-        /// Returns the total token supply.
-        #[ink(message)]
-        pub fn total_supply(&self) -> Balance {
-            self.total_supply
-        }
+    /// Event emitted when a proposal to change the fund's risk limits is executed.
+    #[ink(event)]
+    pub struct RiskLimitsUpdated {
+        #[ink(topic)]
+        id: u32,
+        risk_limits: RiskLimits,
+    }
 
-        /// Returns the account balance for the specified `owner`.
-        ///
-        /// Returns `0` if the account is non-existent.
-        #[ink(message)]
-        pub fn balance_of(&self, owner: AccountId) -> Balance {
-            self.balance_of_impl(&owner)
-        }
+    /// Event emitted when a proposal to change the fund's timelock terms is executed.
+    #[ink(event)]
+    pub struct TimelockUpdated {
+        #[ink(topic)]
+        id: u32,
+        timelock: TimelockConfig,
+    }
 
-        /// Returns the account balance for the specified `owner`.
-        ///
-        /// Returns `0` if the account is non-existent.
-        ///
-        /// # Note
-        ///
-        /// Prefer to call this method over `balance_of` since this
-        /// works using references which are more efficient in Wasm.
-        #[inline]
-        fn balance_of_impl(&self, owner: &AccountId) -> Balance {
-            self.balances.get(owner).unwrap_or_default()
-        }
+    /// Event emitted when a manager action is queued behind the timelock.
+    #[ink(event)]
+    pub struct ActionQueued {
+        #[ink(topic)]
+        id: u32,
+        call: ManagerCall,
+        ztg: Balance,
+        eta: Timestamp,
+    }
 
-        /// Returns the amount which `spender` is still allowed to withdraw from `owner`.
-        ///
-        /// Returns `0` if no allowance has been set.
-        #[ink(message)]
-        pub fn allowance(&self, owner: AccountId, spender: AccountId) -> Balance {
-            self.allowance_impl(&owner, &spender)
-        }
+    /// Event emitted when the manager executes a queued action.
+    #[ink(event)]
+    pub struct ActionExecuted {
+        #[ink(topic)]
+        id: u32,
+    }
 
-        /// Returns the amount which `spender` is still allowed to withdraw from `owner`.
-        ///
-        /// Returns `0` if no allowance has been set.
-        ///
-        /// # Note
-        ///
-        /// Prefer to call this method over `allowance` since this
-        /// works using references which are more efficient in Wasm.
-        #[inline]
-        fn allowance_impl(&self, owner: &AccountId, spender: &AccountId) -> Balance {
-            self.allowances.get((owner, spender)).unwrap_or_default()
-        }
+    /// Event emitted when shareholders veto a queued action.
+    #[ink(event)]
+    pub struct ActionVetoed {
+        #[ink(topic)]
+        id: u32,
+        vetoes: Balance,
+    }
 
-        /// Transfers `value` amount of tokens from the caller's account to account `to`.
-        ///
-        /// On success a `Transfer` event is emitted.
-        ///
-        /// # Errors
-        ///
-        /// Returns `InsufficientBalance` error if there are not enough tokens on
-        /// the caller's account balance.
-        #[ink(message)]
-        pub fn transfer(&mut self, to: AccountId, value: Balance) -> Result<()> {
-            let from = self.env().caller();
-            self.transfer_from_to(&from, &to, value)
-        }
+    /// Event emitted when the manager hands its calls over to a committee, or the committee
+    /// changes its members or threshold.
+    #[ink(event)]
+    pub struct CommitteeChanged {
+        members: Vec<AccountId>,
+        threshold: u32,
+    }
 
-        /// Allows `spender` to withdraw from the caller's account multiple times, up to
-        /// the `value` amount.
-        ///
-        /// If this function is called again it overwrites the current allowance with
-        /// `value`.
+    /// Event emitted when a committee member proposes an action.
+    #[ink(event)]
+    pub struct CommitteeActionProposed {
+        #[ink(topic)]
+        id: u32,
+        #[ink(topic)]
+        proposer: AccountId,
+        action: CommitteeAction,
+    }
+
+    /// Event emitted when a committee member approves a proposal.
+    #[ink(event)]
+    pub struct CommitteeActionApproved {
+        #[ink(topic)]
+        id: u32,
+        #[ink(topic)]
+        member: AccountId,
+    }
+
+    /// Event emitted when a committee member revokes its approval of a proposal.
+    #[ink(event)]
+    pub struct CommitteeApprovalRevoked {
+        #[ink(topic)]
+        id: u32,
+        #[ink(topic)]
+        member: AccountId,
+    }
+
+    /// Event emitted when a committee proposal is executed.
+    #[ink(event)]
+    pub struct CommitteeActionExecuted {
+        #[ink(topic)]
+        id: u32,
+    }
+
+    /// Event emitted when a proposal to replace the code of the fund or its dividend wallet is
+    /// executed.
+    #[ink(event)]
+    pub struct Upgraded {
+        #[ink(topic)]
+        id: u32,
+        #[ink(topic)]
+        contract: AccountId,
+        code_hash: Hash,
+    }
+
+    /// Event emitted when the fund's storage is migrated to a new version.
+    #[ink(event)]
+    pub struct Migrated {
+        from: u32,
+        to: u32,
+    }
+
+    /// Event emitted when the manager reports the fund's net asset value.
+    #[ink(event)]
+    pub struct NavReported {
+        nav: Balance,
+        timestamp: Timestamp,
+    }
+
+    /// Event emitted when fee shares are minted to the fee recipient.
+    #[ink(event)]
+    pub struct FeesAccrued {
+        #[ink(topic)]
+        recipient: AccountId,
+        management_fee: Balance,
+        performance_fee: Balance,
+        timestamp: Timestamp,
+    }
+
+    /// Event emitted when the fund opens a new position.
+    #[ink(event)]
+    pub struct PositionOpened {
+        position: Position,
+    }
+
+    /// Event emitted when the fund closes a position.
+    #[ink(event)]
+    pub struct PositionClosed {
+        position: Position,
+    }
+
+    /// Event emitted when the fund starts liquidating.
+    #[ink(event)]
+    pub struct LiquidationStarted {
+        #[ink(topic)]
+        caller: AccountId,
+        timestamp: Timestamp,
+    }
+
+    /// Event emitted when a user redeems shares for ZTG during liquidation.
+    #[ink(event)]
+    pub struct Redeemed {
+        #[ink(topic)]
+        user: AccountId,
+        shares: Balance,
+        amount: Balance,
+    }
+
+    /// Event emitted when a user redeems shares for their slice of the fund's assets.
+    #[ink(event)]
+    pub struct RedeemedInKind {
+        #[ink(topic)]
+        user: AccountId,
+        shares: Balance,
+        ztg: Balance,
+        assets: Vec<(ZeitgeistAsset, Balance)>,
+    }
+
+    /// Event emitted when a user is refunded their ZTG after a failed raise.
+    #[ink(event)]
+    pub struct Refunded {
+        #[ink(topic)]
+        user: AccountId,
+        amount: Balance,
+    }
+
+    /// Event emitted when a user asks to subscribe to an open-ended fund.
+    #[ink(event)]
+    pub struct SubscriptionRequested {
+        #[ink(topic)]
+        user: AccountId,
+        amount: Balance,
+    }
+
+    /// Event emitted when a subscription is settled at a NAV strike.
+    #[ink(event)]
+    pub struct SubscriptionSettled {
+        #[ink(topic)]
+        user: AccountId,
+        amount: Balance,
+        shares: Balance,
+    }
+
+    /// Event emitted when a user asks to redeem shares of an open-ended fund.
+    #[ink(event)]
+    pub struct RedemptionRequested {
+        #[ink(topic)]
+        user: AccountId,
+        shares: Balance,
+    }
+
+    /// Event emitted when a redemption is settled at a NAV strike, in full or in part.
+    #[ink(event)]
+    pub struct RedemptionSettled {
+        #[ink(topic)]
+        user: AccountId,
+        shares: Balance,
+        amount: Balance,
+    }
+
+    /// The fund error types.
+    #[allow(clippy::enum_variant_names)]
+    #[derive(Debug, PartialEq, Eq, scale::Encode, scale::Decode)]
+    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+    pub enum Error {
+        /// Returned if not enough balance to fulfill a request is available.
+        InsufficientBalance,
+        /// Returned if not enough allowance to fulfill a request is available.
+        InsufficientAllowance,
+        /// Returned if only the manager is allowed to call the function.
+        OnlyManagerAllowed,
+        /// Returned if only the guardian is allowed to call the function.
+        OnlyGuardianAllowed,
+        /// Returned if the caller is not the account that the manager proposed as its successor.
+        NotPendingManager,
+        /// Returned if the caller does not hold the role that the function needs.
+        MissingRole(Role),
+        /// Returned if the message is not allowed in the fund's current state.
+        NotAllowedInState(FundState),
+        FundingTooMuch,
+        ManagerSharesAreLocked,
+        /// Returned if a runtime call was rejected, with the call and the reason.
+        CallRuntimeFailed {
+            call: RuntimeCallKind,
+            reason: EnvFailure,
+        },
+        /// Returned if calling the dividend wallet failed.
+        DividendWalletCallFailed(EnvFailure),
+        /// Returned for any other error from `pallet-contracts`.
+        Env(EnvFailure),
+        /// Returned if the dividend wallet could not distribute a dividend, with its reason.
+        DividendDistributionError(WalletError),
+        /// Returned if the dividend wallet could not be upgraded, with its reason.
+        DividendWalletUpgradeError(WalletError),
+        /// Returned if the fund is used before its storage has been migrated after an upgrade.
+        MigrationRequired,
+        /// Returned if `migrate()` is called when the storage is already up to date.
+        AlreadyMigrated,
+        /// Returned if the caller has no shares to be refunded.
+        NothingToRefund,
+        /// Returned if sending ZTG out of the contract failed.
+        TransferFailed,
+        /// Returned if liquidation is attempted before maturity.
+        MaturityNotReached,
+        /// Returned if someone other than the manager liquidates during the grace period.
+        GracePeriodNotOver,
+        /// Returned if the manager sends a call that is not an exit while liquidating.
+        OnlyExitsWhileLiquidating,
+        /// Returned if zero shares are redeemed.
+        ZeroShares,
+        /// Returned if the caller held no shares at the proposal's snapshot.
+        NoVotingPower,
+        /// Returned if a manager proposal does not exist.
+        ProposalNotFound,
+        /// Returned if the caller already voted on a manager proposal.
+        AlreadyVoted,
+        /// Returned if a manager proposal was already executed.
+        ProposalAlreadyExecuted,
+        /// Returned if a manager proposal is executed before reaching quorum.
+        QuorumNotReached,
+        /// Returned if a manager proposal is executed before its timelock has passed.
+        TimelockNotOver,
+        /// Returned if risk limits allow more than 100% exposure.
+        InvalidRiskLimits,
+        /// Returned if the manager buys into a market that is not on the allow-list.
+        MarketNotAllowed,
+        /// Returned if the manager trades in a pool that is not on the allow-list.
+        PoolNotAllowed,
+        /// Returned if a trade would put more than the maximum ZTG into a single market.
+        MarketExposureTooHigh,
+        /// Returned if a trade would put more than the maximum ZTG into markets and pools.
+        TotalExposureTooHigh,
+        /// Returned if a queued action does not exist.
+        ActionNotFound,
+        /// Returned if a queued action was already executed or vetoed.
+        ActionNotQueued,
+        /// Returned if open-ended dealing is used on a closed-ended fund.
+        NotOpenEnded,
+        /// Returned if a subscription or redemption is requested outside of a dealing window.
+        WindowClosed,
+        /// Returned if a subscription would exceed the ZTG cap of the dealing window.
+        SubscriptionCapReached,
+        /// Returned if no ZTG is sent with a subscription.
+        ZeroAmount,
+        /// Returned if the manager sends a call directly while a committee manages the fund.
+        CommitteeApprovalRequired,
+        /// Returned if the manager hands over to a committee a second time.
+        CommitteeAlreadyEnabled,
+        /// Returned if the caller is not a member of the manager committee.
+        NotCommitteeMember,
+        /// Returned if a committee would have duplicate members or an unreachable threshold.
+        InvalidCommittee,
+        /// Returned if a committee member approves a proposal a second time.
+        AlreadyApproved,
+        /// Returned if a committee member revokes an approval that it did not give.
+        NotApproved,
+        /// Returned if a committee proposal is executed with fewer approvals than the threshold.
+        NotEnoughApprovals,
+        /// Returned if settling a request overflows a share or ZTG amount.
+        Overflow,
+    }
+
+    /// The runtime calls that a fund dispatches, to tell which one failed.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, scale::Encode, scale::Decode)]
+    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+    pub enum RuntimeCallKind {
+        /// A manager call into the Swaps pallet.
+        Swap,
+        /// A manager call into the PredictionMarkets pallet.
+        PredictionMarket,
+        /// A transfer of a dividend into the dividend wallet.
+        DividendTransfer,
+        /// A transfer of assets to a user that redeems in kind.
+        InKindTransfer,
+    }
+
+    impl From<EnvError> for Error {
+        fn from(e: EnvError) -> Self {
+            Error::Env(e.into())
+        }
+    }
+
+    /// Maps the fund's errors onto PSP22 errors. Errors without a PSP22 variant become fixed
+    /// `Custom` reasons, since the reasons are part of the token's ABI.
+    impl From<Error> for PSP22Error {
+        fn from(e: Error) -> Self {
+            let reason = match e {
+                Error::InsufficientBalance => return PSP22Error::InsufficientBalance,
+                Error::InsufficientAllowance => return PSP22Error::InsufficientAllowance,
+                Error::ManagerSharesAreLocked => "ManagerSharesAreLocked",
+                Error::NotAllowedInState(_) => "NotAllowedInState",
+                Error::MigrationRequired => "MigrationRequired",
+                Error::Env(_) => "Env",
+                _ => "Other",
+            };
+            PSP22Error::Custom(reason.into())
+        }
+    }
+
+    /// The fund result type.
+    pub type Result<T> = core::result::Result<T, Error>;
+
+    // endregion
+
+    impl ZeitFund {
+        /// Constructor that initializes the `bool` value to the given `init_value`.
+        #[ink(constructor)]
+        #[allow(clippy::too_many_arguments)]
+        pub fn new(
+            manager: AccountId,
+            guardian: AccountId,
+            metadata: TokenMetadata,
+            total_shares: Balance,
+            manager_vesting: Option<ManagerVesting>,
+            funding_deadline: Timestamp,
+            maturity: Timestamp,
+            fees: FeeConfig,
+            risk_limits: RiskLimits,
+            timelock: TimelockConfig,
+            open_ended: Option<OpenEndedConfig>,
+            dividend_wallet_hash: Hash,
+        ) -> Self {
+            // Constructs wallet, salted with the fund's own address so that every fund's wallet
+            // is unique. See `predict_dividend_wallet_address`.
+            let dividend_wallet = DividendWalletRef::new()
+                .code_hash(dividend_wallet_hash)
+                .endowment(0)
+                .salt_bytes(Self::env().account_id())
+                .instantiate();
+
+            Self::init(
+                manager,
+                guardian,
+                metadata,
+                total_shares,
+                manager_vesting,
+                funding_deadline,
+                maturity,
+                fees,
+                risk_limits,
+                timelock,
+                open_ended,
+                dividend_wallet,
+            )
+        }
+
+        /// Constructor that takes in a dividend wallet instead of creating its own.
         ///
-        /// An `Approval` event is emitted.
-        #[ink(message)]
-        pub fn approve(&mut self, spender: AccountId, value: Balance) -> Result<()> {
-            let owner = self.env().caller();
-            self.allowances.insert((&owner, &spender), &value);
-            self.env().emit_event(Approval {
-                owner,
-                spender,
-                value,
-            });
-            Ok(())
+        /// The dividend wallet must implement the
+        /// `distribute(dest: AccountId, currency_id: ZeitgeistAsset, amount: u128) -> Result<(), Error>`
+        /// function.
+        #[ink(constructor)]
+        #[allow(clippy::too_many_arguments)]
+        pub fn no_instantiation(
+            manager: AccountId,
+            guardian: AccountId,
+            metadata: TokenMetadata,
+            total_shares: Balance,
+            manager_vesting: Option<ManagerVesting>,
+            funding_deadline: Timestamp,
+            maturity: Timestamp,
+            fees: FeeConfig,
+            risk_limits: RiskLimits,
+            timelock: TimelockConfig,
+            open_ended: Option<OpenEndedConfig>,
+            dividend_wallet: AccountId,
+        ) -> Self {
+            Self::init(
+                manager,
+                guardian,
+                metadata,
+                total_shares,
+                manager_vesting,
+                funding_deadline,
+                maturity,
+                fees,
+                risk_limits,
+                timelock,
+                open_ended,
+                DividendWalletRef::from_account_id(dividend_wallet),
+            )
         }
 
-        /// Transfers `value` tokens on the behalf of `from` to the account `to`.
+        /// Initializes the storage shared by both constructors.
+        #[allow(clippy::too_many_arguments)]
+        fn init(
+            manager: AccountId,
+            guardian: AccountId,
+            metadata: TokenMetadata,
+            total_shares: Balance,
+            manager_vesting: Option<ManagerVesting>,
+            funding_deadline: Timestamp,
+            maturity: Timestamp,
+            fees: FeeConfig,
+            risk_limits: RiskLimits,
+            timelock: TimelockConfig,
+            open_ended: Option<OpenEndedConfig>,
+            dividend_wallet: DividendWalletRef,
+        ) -> Self {
+            assert!(
+                Balance::from(fees.management_fee_bps) <= BASIS_POINTS
+                    && Balance::from(fees.performance_fee_bps) <= BASIS_POINTS,
+                "Fees cannot be more than 100%."
+            );
+            assert!(
+                risk_limits.is_valid(),
+                "Risk limits cannot allow more than 100% exposure."
+            );
+            assert!(
+                open_ended.as_ref().is_none_or(OpenEndedConfig::is_valid),
+                "Dealing windows must fit within their interval."
+            );
+            assert!(
+                manager_vesting
+                    .as_ref()
+                    .is_none_or(ManagerVesting::is_valid),
+                "The vesting cliff must be within the vesting duration."
+            );
+
+            let mut fund = Self {
+                manager,
+                metadata,
+                total_supply: total_shares,
+                balances: Default::default(),
+                allowances: Default::default(),
+                funding_amount: 0,
+                funding_goal: total_shares,
+                funding_deadline,
+                maturity,
+                state: FundState::Fundraising,
+                guardian,
+                paused_from: FundState::Fundraising,
+                lock_manager_shares: false,
+                manager_vesting: Default::default(),
+                manager_vesting_shares: Default::default(),
+                manager_vesting_account: Default::default(),
+                dividend_wallet,
+                dividend_assets: Vec::new(),
+                dividend_asset_count: Default::default(),
+                dividend_asset_list: Default::default(),
+                dividends_per_share: Default::default(),
+                dividend_debt: Default::default(),
+                dividend_credit: Default::default(),
+                last_claimed_dividend: Default::default(),
+                checkpoint_counts: Default::default(),
+                checkpoints: Default::default(),
+                supply_checkpoint_count: 0,
+                supply_checkpoints: Default::default(),
+                manager_proposals: Default::default(),
+                manager_proposal_count: 0,
+                manager_votes: Default::default(),
+                fees,
+                nav: 0,
+                high_water_mark: NAV_PRECISION,
+                management_fee_accrued_at: 0,
+                performance_fee_crystallised_at: 0,
+                positions: Vec::new(),
+                position_count: Default::default(),
+                position_list: Default::default(),
+                position_index: Default::default(),
+                pool_shares: Default::default(),
+                asset_holdings: Default::default(),
+                complete_sets: Default::default(),
+                risk_limits,
+                market_exposure: Default::default(),
+                pool_exposure: Default::default(),
+                total_exposure: 0,
+                timelock: Default::default(),
+                queued_actions: Default::default(),
+                queued_action_count: Default::default(),
+                action_vetoes: Default::default(),
+                open_ended: Default::default(),
+                activated_at: Default::default(),
+                subscription_queue: Default::default(),
+                subscription_queue_start: Default::default(),
+                subscription_queue_end: Default::default(),
+                pending_subscriptions: Default::default(),
+                window_subscriptions: Default::default(),
+                redemption_queue: Default::default(),
+                redemption_queue_start: Default::default(),
+                redemption_queue_end: Default::default(),
+                pending_redemptions: Default::default(),
+                committee_size: Default::default(),
+                committee_members: Default::default(),
+                committee_threshold: Default::default(),
+                committee_proposals: Default::default(),
+                committee_proposal_count: Default::default(),
+                committee_approvals: Default::default(),
+                roles: Default::default(),
+                pending_manager: Default::default(),
+                storage_version: Default::default(),
+            };
+            fund.storage_version.set(&STORAGE_VERSION);
+            if let Some(manager_vesting) = manager_vesting {
+                fund.manager_vesting.set(&manager_vesting);
+            }
+            fund.manager_vesting_account.set(&manager);
+            fund.timelock.set(&timelock);
+            if let Some(open_ended) = open_ended {
+                fund.open_ended.set(&open_ended);
+            }
+
+            // Give the zero address itself the total supply, to be distributed later
+            let zero = AccountId::from([0; 32]);
+            fund.balances.insert(zero, &total_shares);
+            fund.write_checkpoint(&zero, total_shares);
+            fund.write_supply_checkpoint(total_shares);
+
+            fund
+        }
+
+        // region: Shares
+
+        /// Returns the account balance for the specified `owner`.
         ///
-        /// This can be used to allow a contract to transfer tokens on ones behalf and/or
-        /// to charge fees in sub-currencies, for example.
+        /// Returns `0` if the account is non-existent.
         ///
-        /// On success a `Transfer` event is emitted.
+        /// # Note
         ///
-        /// # Errors
+        /// Prefer to call this method over `balance_of` since this
+        /// works using references which are more efficient in Wasm.
+        #[inline]
+        fn balance_of_impl(&self, owner: &AccountId) -> Balance {
+            self.balances.get(owner).unwrap_or_default()
+        }
+
+        /// Returns the amount which `spender` is still allowed to withdraw from `owner`.
         ///
-        /// Returns `InsufficientAllowance` error if there are not enough tokens allowed
-        /// for the caller to withdraw from `from`.
+        /// Returns `0` if no allowance has been set.
         ///
-        /// Returns `InsufficientBalance` error if there are not enough tokens on
-        /// the account balance of `from`.
-        #[ink(message)]
-        pub fn transfer_from(
+        /// # Note
+        ///
+        /// Prefer to call this method over `allowance` since this
+        /// works using references which are more efficient in Wasm.
+        #[inline]
+        fn allowance_impl(&self, owner: &AccountId, spender: &AccountId) -> Balance {
+            self.allowances.get((owner, spender)).unwrap_or_default()
+        }
+
+        /// Sets the amount which `spender` is allowed to withdraw from `owner`.
+        ///
+        /// An `Approval` event is emitted.
+        fn approve_impl(&mut self, owner: &AccountId, spender: &AccountId, value: Balance) {
+            self.allowances.insert((owner, spender), &value);
+            self.emit(Approval {
+                owner: *owner,
+                spender: *spender,
+                value,
+            });
+        }
+
+        /// Transfers `value` tokens from `from` to `to` if the fund allows transfers and `to`
+        /// is not the zero address.
+        fn transfer_impl(
             &mut self,
-            from: AccountId,
-            to: AccountId,
+            from: &AccountId,
+            to: &AccountId,
             value: Balance,
-        ) -> Result<()> {
-            let caller = self.env().caller();
-            let allowance = self.allowance_impl(&from, &caller);
-            if allowance < value {
-                return Err(Error::InsufficientAllowance);
+        ) -> core::result::Result<(), PSP22Error> {
+            self.ensure_allowed(Action::Transfer)?;
+            if to == &AccountId::from([0; 32]) {
+                return Err(PSP22Error::ZeroRecipientAddress);
             }
-            self.transfer_from_to(&from, &to, value)?;
-            self.allowances
-                .insert((&from, &caller), &(allowance - value));
-            Ok(())
+            Ok(self.transfer_from_to(from, to, value)?)
         }
 
         /// Transfers `value` amount of tokens from the caller's account to account `to`.
@@ -328,19 +1458,35 @@ mod zeit_fund {
                 return Err(Error::InsufficientBalance);
             }
 
-            if from == &self.manager && self.lock_manager_shares {
+            if from == &self.manager_vesting_account()
+                && from_balance - value < self.vesting_locked_shares()
+            {
                 return Err(Error::ManagerSharesAreLocked);
             }
 
-            // Ensure that dividend is claimed by the from & to
-            // NOTE: this forces the "to" to receive the ZTG
-            self.claim_dividend(from.clone())?;
-            self.claim_dividend(to.clone())?;
+            self.move_shares(from, to, from_balance, value)
+        }
+
+        /// Moves `value` shares from `from` to `to` without any lock checks.
+        ///
+        /// `from_balance` must be the current balance of `from` and at least `value`.
+        fn move_shares(
+            &mut self,
+            from: &AccountId,
+            to: &AccountId,
+            from_balance: Balance,
+            value: Balance,
+        ) -> Result<()> {
+            // Dividends issued so far belong to the holders before this transfer
+            self.settle_dividend(from);
+            self.settle_dividend(to);
 
             self.balances.insert(from, &(from_balance - value));
+            self.write_checkpoint(from, from_balance - value);
             let to_balance = self.balance_of_impl(to);
             self.balances.insert(to, &(to_balance + value));
-            self.env().emit_event(Transfer {
+            self.write_checkpoint(to, to_balance + value);
+            self.emit(Transfer {
                 from: Some(*from),
                 to: Some(*to),
                 value,
@@ -350,354 +1496,5434 @@ mod zeit_fund {
 
         // endregion
 
-        // region: Funding
-
-        /// Allows users to send ZTG to fund the contract in return for shares.
-        #[ink(message, payable)]
-        pub fn fund(&mut self) -> Result<()> {
-            let v = self.env().transferred_value();
-            // NOTE: potential DOS here
-            if v + self.funding_amount > self.total_supply {
-                return Err(Error::FundingTooMuch);
-            }
-
-            // Mint to user
-            self.transfer_from_to(&AccountId::from([0; 32]), &self.env().caller(), v)?;
-            self.funding_amount += v;
+        // region: Checkpoints
 
-            Ok(())
+        /// Returns the balance of `owner` at the end of `timestamp`.
+        ///
+        /// Returns `0` if the account had no shares at that time.
+        #[ink(message)]
+        pub fn balance_of_at(&self, owner: AccountId, timestamp: Timestamp) -> Balance {
+            let count = self.checkpoint_counts.get(owner).unwrap_or(0);
+            Self::checkpoint_at(count, timestamp, |i| self.checkpoints.get((owner, i)))
         }
 
-        /// The initial funding amount in ZTG required for the fund to start.
+        /// Returns the total token supply at the end of `timestamp`.
         #[ink(message)]
-        pub fn initial_funding_amount(&self) -> u128 {
-            self.funding_amount
+        pub fn total_supply_at(&self, timestamp: Timestamp) -> Balance {
+            Self::checkpoint_at(self.supply_checkpoint_count, timestamp, |i| {
+                self.supply_checkpoints.get(i)
+            })
         }
 
-        /// True if the contract has been completely funded, false if otherwise.
-        #[ink(message)]
-        pub fn is_funded(&self) -> bool {
-            self.funding_amount == self.total_supply
+        /// The shares that were not held by the zero address at the end of `timestamp`.
+        fn outstanding_shares_at(&self, timestamp: Timestamp) -> Balance {
+            self.total_supply_at(timestamp)
+                - self.balance_of_at(AccountId::from([0; 32]), timestamp)
         }
 
-        #[inline]
-        fn must_be_funded(&self) -> Result<()> {
-            if !self.is_funded() {
-                return Err(Error::MustBeFunded);
+        /// Records `balance` as the balance of `owner` at the current timestamp.
+        fn write_checkpoint(&mut self, owner: &AccountId, balance: Balance) {
+            let timestamp = self.env().block_timestamp();
+            let count = self.checkpoint_counts.get(owner).unwrap_or(0);
+            let last = count
+                .checked_sub(1)
+                .and_then(|i| self.checkpoints.get((owner, i)));
+
+            // Overwrite the latest checkpoint if it was written at the same timestamp
+            match last {
+                Some((last_timestamp, _)) if last_timestamp == timestamp => {
+                    self.checkpoints
+                        .insert((owner, count - 1), &(timestamp, balance));
+                }
+                _ => {
+                    self.checkpoints
+                        .insert((owner, count), &(timestamp, balance));
+                    self.checkpoint_counts.insert(owner, &(count + 1));
+                }
             }
-            Ok(())
         }
 
-        // endregion
+        /// Records `supply` as the total supply at the current timestamp.
+        fn write_supply_checkpoint(&mut self, supply: Balance) {
+            let timestamp = self.env().block_timestamp();
+            let count = self.supply_checkpoint_count;
+            let last = count
+                .checked_sub(1)
+                .and_then(|i| self.supply_checkpoints.get(i));
 
-        // region: Fund Management
+            // Overwrite the latest checkpoint if it was written at the same timestamp
+            match last {
+                Some((last_timestamp, _)) if last_timestamp == timestamp => {
+                    self.supply_checkpoints
+                        .insert(count - 1, &(timestamp, supply));
+                }
+                _ => {
+                    self.supply_checkpoints.insert(count, &(timestamp, supply));
+                    self.supply_checkpoint_count = count + 1;
+                }
+            }
+        }
 
-        /// Allows the manager to send a call into the Swaps pallet.
+        /// Binary searches `count` checkpoints, read with `get`, for the value at the end of
+        /// `timestamp`.
+        fn checkpoint_at(
+            count: u32,
+            timestamp: Timestamp,
+            get: impl Fn(u32) -> Option<Checkpoint>,
+        ) -> Balance {
+            // Find the first checkpoint that is after the timestamp
+            let mut low = 0;
+            let mut high = count;
+            while low < high {
+                let mid = low + (high - low) / 2;
+                let (checkpoint_timestamp, _) = get(mid).unwrap_or_default();
+                if checkpoint_timestamp > timestamp {
+                    high = mid;
+                } else {
+                    low = mid + 1;
+                }
+            }
+
+            match low.checked_sub(1) {
+                Some(index) => get(index).unwrap_or_default().1,
+                None => 0,
+            }
+        }
+
+        // endregion
+
+        // region: Lifecycle
+
+        /// The current lifecycle state of the fund.
+        ///
+        /// A fund that is still fundraising after its funding deadline is reported as `Failed`,
+        /// even before a message has stored the transition.
         #[ink(message)]
-        pub fn swap_call(&mut self, call: SwapsCall) -> Result<()> {
-            self.only_manager()?;
-            self.must_be_funded()?;
+        pub fn state(&self) -> FundState {
+            // A raise fails at the deadline even if paused, so that refunds are always possible
+            let raising = self.state == FundState::Fundraising
+                || (self.state == FundState::Paused && self.paused_from == FundState::Fundraising);
+            if raising && self.env().block_timestamp() > self.funding_deadline {
+                return FundState::Failed;
+            }
+            self.state
+        }
 
-            self.env()
-                .call_runtime(&RuntimeCall::Swaps(call))
-                .map_err(Into::<Error>::into)?;
+        /// True if `action` can currently be performed.
+        #[ink(message)]
+        pub fn is_allowed(&self, action: Action) -> bool {
+            self.state().allows(action)
+        }
+
+        /// Halts the fund in an emergency, such as a bug or a runtime upgrade that changes pallet
+        /// indices. Only holders of the `Guardian` role can pause.
+        ///
+        /// While paused, dividends can still be claimed, and refunds become possible if the
+        /// funding deadline passes.
+        #[ink(message)]
+        pub fn pause(&mut self) -> Result<()> {
+            self.only_guardian()?;
+            self.ensure_allowed(Action::Pause)?;
+
+            let from = self.state;
+            self.paused_from = from;
+            self.set_state(FundState::Paused);
+            self.emit(Paused {
+                guardian: self.env().caller(),
+                from,
+            });
 
             Ok(())
         }
 
-        /// Allows the manager to send a call into the PredictionMarkets pallet.
+        /// Resumes a paused fund in the state that it was paused from. Only holders of the
+        /// `Guardian` role can unpause.
         #[ink(message)]
-        pub fn prediction_market_call(&mut self, call: PredictionMarketsCall) -> Result<()> {
-            self.only_manager()?;
-            self.must_be_funded()?;
+        pub fn unpause(&mut self) -> Result<()> {
+            self.only_guardian()?;
+            self.ensure_allowed(Action::Unpause)?;
 
-            self.env()
-                .call_runtime(&RuntimeCall::PredictionMarkets(call))
-                .map_err(Into::<Error>::into)?;
+            let to = self.paused_from;
+            self.set_state(to);
+            self.emit(Unpaused {
+                guardian: self.env().caller(),
+                to,
+            });
 
             Ok(())
         }
 
+        /// Hands the guardian role to `new_guardian`. Only the guardian can do so. Accounts
+        /// that were granted the `Guardian` role keep it.
+        #[ink(message)]
+        pub fn set_guardian(&mut self, new_guardian: AccountId) -> Result<()> {
+            if self.env().caller() != self.guardian {
+                return Err(Error::OnlyGuardianAllowed);
+            }
+            self.guardian = new_guardian;
+            Ok(())
+        }
+
+        /// The account that can pause the fund in an emergency.
+        #[ink(message)]
+        pub fn guardian(&self) -> AccountId {
+            self.guardian
+        }
+
+        #[inline]
+        fn only_guardian(&self) -> Result<()> {
+            if !self.has_role(Role::Guardian, self.env().caller()) {
+                return Err(Error::OnlyGuardianAllowed);
+            }
+            Ok(())
+        }
+
+        /// Stores the effective state and returns an error if `action` is not allowed in it.
+        fn ensure_allowed(&mut self, action: Action) -> Result<()> {
+            if self.storage_version() != STORAGE_VERSION {
+                return Err(Error::MigrationRequired);
+            }
+            let state = self.state();
+            if state != self.state {
+                self.set_state(state);
+            }
+            if !state.allows(action) {
+                return Err(Error::NotAllowedInState(state));
+            }
+            Ok(())
+        }
+
+        /// The stored state, or the state that the fund was paused from if it is paused.
+        fn unpaused_state(&self) -> FundState {
+            match self.state {
+                FundState::Paused => self.paused_from,
+                state => state,
+            }
+        }
+
+        fn set_state(&mut self, to: FundState) {
+            let from = self.state;
+            self.state = to;
+            self.emit(StateChanged { from, to });
+        }
+
         // endregion
 
-        // region: Dividends
+        // region: Funding
+
+        /// Allows users to send ZTG to fund the contract in return for shares.
+        ///
+        /// Once an open-ended fund is active, this queues a subscription instead, which is
+        /// settled at the next NAV strike.
+        #[ink(message, payable)]
+        pub fn fund(&mut self) -> Result<()> {
+            if self.open_ended().is_some() && self.state() != FundState::Fundraising {
+                return self.subscribe();
+            }
+            self.ensure_allowed(Action::Fund)?;
+            let v = self.env().transferred_value();
+            // NOTE: potential DOS here
+            if v + self.funding_amount > self.funding_goal {
+                return Err(Error::FundingTooMuch);
+            }
+
+            // Mint to user
+            self.transfer_from_to(&AccountId::from([0; 32]), &self.env().caller(), v)?;
+            self.funding_amount += v;
 
-        /// Allows the manager to issue a dividend of a specific amount.
+            if self.is_funded() {
+                self.set_state(FundState::Active);
+
+                // Fees are charged from the start of the fund's activity
+                let timestamp = self.env().block_timestamp();
+                self.activated_at.set(&timestamp);
+                self.manager_vesting_shares
+                    .set(&self.balance_of_impl(&self.manager_vesting_account()));
+                self.management_fee_accrued_at = timestamp;
+                self.performance_fee_crystallised_at = timestamp;
+            }
+
+            Ok(())
+        }
+
+        /// The initial funding amount in ZTG required for the fund to start.
         #[ink(message)]
-        pub fn issue_dividend(&mut self, amount: Balance) -> Result<()> {
-            self.only_manager()?;
-            self.must_be_funded()?;
+        pub fn initial_funding_amount(&self) -> u128 {
+            self.funding_amount
+        }
+
+        /// True if the contract has been completely funded, false if otherwise.
+        #[ink(message)]
+        pub fn is_funded(&self) -> bool {
+            self.funding_amount == self.funding_goal
+        }
+
+        /// The timestamp after which the fund no longer accepts funding.
+        #[ink(message)]
+        pub fn funding_deadline(&self) -> Timestamp {
+            self.funding_deadline
+        }
+
+        /// Returns the caller's ZTG if the fund did not reach its goal before the funding
+        /// deadline. The caller's shares are burned back to the zero address.
+        ///
+        /// On success a `Refunded` event is emitted.
+        #[ink(message)]
+        pub fn refund(&mut self) -> Result<Balance> {
+            self.ensure_allowed(Action::Refund)?;
+
+            let caller = self.env().caller();
+            let shares = self.balance_of_impl(&caller);
+            if shares == 0 {
+                return Err(Error::NothingToRefund);
+            }
+
+            // Burn shares, ignoring the manager lock since the fund never started
+            self.move_shares(&caller, &AccountId::from([0; 32]), shares, shares)?;
+            self.funding_amount -= shares;
 
-            // Send to dividend wallet
+            // Shares were minted 1:1 for ZTG
             self.env()
-                .call_runtime(&RuntimeCall::AssetManager(AssetManagerCall::Transfer {
-                    dest: self.dividend_wallet.to_account_id().into(),
-                    currency_id: ZeitgeistAsset::Ztg,
-                    amount,
-                }))
-                .map_err(Into::<Error>::into)?;
+                .transfer(caller, shares)
+                .map_err(|_| Error::TransferFailed)?;
 
-            // Add to dividend list
-            let timestamp = self.env().block_timestamp();
-            self.dividends.push((timestamp, amount));
+            self.emit(Refunded {
+                user: caller,
+                amount: shares,
+            });
+
+            Ok(shares)
+        }
+
+        /// The time at which the fund was completely funded, or 0 if it has not been.
+        #[ink(message)]
+        pub fn activated_at(&self) -> Timestamp {
+            self.activated_at.get().unwrap_or(0)
+        }
+
+        // endregion
+
+        // region: Committee
+
+        /// Hands the manager's calls over to a committee of `members`, `threshold` of which must
+        /// approve each swap, prediction market call and dividend. Only the manager can hand
+        /// over, once. Afterwards, only the committee can change its members.
+        #[ink(message)]
+        pub fn enable_committee(&mut self, members: Vec<AccountId>, threshold: u32) -> Result<()> {
+            self.only_manager()?;
+            if self.has_committee() {
+                return Err(Error::CommitteeAlreadyEnabled);
+            }
+            Self::ensure_valid_committee(&members, threshold)?;
 
-            // Emit dividend event
-            self.env().emit_event(DividendIssued { amount, timestamp });
+            self.set_committee(members, threshold);
 
             Ok(())
         }
 
-        /// Claims a dividend for the caller.
+        /// Proposes `action` to the committee, approving it on behalf of the caller. Only
+        /// committee members can propose.
         #[ink(message)]
-        pub fn claim(&mut self) -> Result<Balance> {
-            self.claim_dividend(self.env().caller())
+        pub fn propose_committee_action(&mut self, action: CommitteeAction) -> Result<u32> {
+            self.only_committee_member()?;
+            let proposer = self.env().caller();
+            self.committee_after(&action)?;
+
+            let id = self.committee_proposal_count();
+            self.committee_proposals.insert(
+                id,
+                &CommitteeProposal {
+                    proposer,
+                    action: action.clone(),
+                    executed: false,
+                },
+            );
+            self.committee_proposal_count.set(&(id + 1));
+            self.emit(CommitteeActionProposed {
+                id,
+                proposer,
+                action,
+            });
+
+            self.approve_committee_action(id)?;
+
+            Ok(id)
         }
 
-        /// Claims a dividend for a specific user
-        fn claim_dividend(&mut self, caller: AccountId) -> Result<Balance> {
-            // Calculate amount of dividend since last claim
-            let dividend = self.calc_dividend(caller);
+        /// Approves a committee proposal on behalf of the caller.
+        #[ink(message)]
+        pub fn approve_committee_action(&mut self, id: u32) -> Result<()> {
+            self.only_committee_member()?;
+            let member = self.env().caller();
+            self.pending_committee_proposal(id)?;
+            if self.committee_approvals.contains((id, member)) {
+                return Err(Error::AlreadyApproved);
+            }
 
-            // Sets last claimed dividend
-            let block_timestamp = self.env().block_timestamp();
-            self.last_claimed_dividend.insert(caller, &block_timestamp);
+            self.committee_approvals.insert((id, member), &());
+            self.emit(CommitteeActionApproved { id, member });
 
-            // Claim dividend from dividend wallet
-            if dividend > 0 {
-                let res = self.dividend_wallet.distribute(caller, dividend);
-                if !res {
-                    return Err(Error::DividendDistributionError);
+            Ok(())
+        }
+
+        /// Revokes the caller's approval of a committee proposal that has not been executed.
+        #[ink(message)]
+        pub fn revoke_committee_approval(&mut self, id: u32) -> Result<()> {
+            self.only_committee_member()?;
+            let member = self.env().caller();
+            self.pending_committee_proposal(id)?;
+            if !self.committee_approvals.contains((id, member)) {
+                return Err(Error::NotApproved);
+            }
+
+            self.committee_approvals.remove((id, member));
+            self.emit(CommitteeApprovalRevoked { id, member });
+
+            Ok(())
+        }
+
+        /// Executes a committee proposal once enough current members have approved it. Any
+        /// committee member can execute.
+        #[ink(message)]
+        pub fn execute_committee_action(&mut self, id: u32) -> Result<()> {
+            self.only_committee_member()?;
+            let mut proposal = self.pending_committee_proposal(id)?;
+            if self.committee_approvals(id) < self.committee_threshold() {
+                return Err(Error::NotEnoughApprovals);
+            }
+
+            proposal.executed = true;
+            self.committee_proposals.insert(id, &proposal);
+
+            match proposal.action {
+                CommitteeAction::Call(call) => self.execute_or_queue(call)?,
+                action => {
+                    let (members, threshold) = self.committee_after(&action)?;
+                    self.set_committee(members, threshold);
                 }
+            }
+            self.emit(CommitteeActionExecuted { id });
 
-                self.env().emit_event(DividendClaimed {
-                    user: caller,
-                    amount: dividend,
-                    timestamp: block_timestamp,
+            Ok(())
+        }
+
+        /// Returns the committee proposal with the given id, or an error if it does not exist or
+        /// was already executed.
+        fn pending_committee_proposal(&self, id: u32) -> Result<CommitteeProposal> {
+            let proposal = self
+                .committee_proposals
+                .get(id)
+                .ok_or(Error::ProposalNotFound)?;
+            if proposal.executed {
+                return Err(Error::ProposalAlreadyExecuted);
+            }
+            Ok(proposal)
+        }
+
+        /// The committee members and threshold after `action` is executed.
+        fn committee_after(&self, action: &CommitteeAction) -> Result<(Vec<AccountId>, u32)> {
+            let mut members = self.committee();
+            let mut threshold = self.committee_threshold();
+            match action {
+                CommitteeAction::Call(_) => {}
+                CommitteeAction::AddMember(member) => members.push(*member),
+                CommitteeAction::RemoveMember(member) => {
+                    let index = members
+                        .iter()
+                        .position(|m| m == member)
+                        .ok_or(Error::NotCommitteeMember)?;
+                    members.remove(index);
+                }
+                CommitteeAction::SetThreshold(new_threshold) => threshold = *new_threshold,
+            }
+            Self::ensure_valid_committee(&members, threshold)?;
+            Ok((members, threshold))
+        }
+
+        /// Replaces the committee members and threshold.
+        fn set_committee(&mut self, members: Vec<AccountId>, threshold: u32) {
+            let size = self.committee_size.get().unwrap_or(0);
+            for index in members.len() as u32..size {
+                self.committee_members.remove(index);
+            }
+            for (index, member) in members.iter().enumerate() {
+                self.committee_members.insert(index as u32, member);
+            }
+            self.committee_size.set(&(members.len() as u32));
+            self.committee_threshold.set(&threshold);
+            self.emit(CommitteeChanged { members, threshold });
+        }
+
+        /// True if a committee manages the fund instead of the manager alone.
+        fn has_committee(&self) -> bool {
+            self.committee_size.get().unwrap_or(0) > 0
+        }
+
+        fn ensure_valid_committee(members: &[AccountId], threshold: u32) -> Result<()> {
+            let unique = members
+                .iter()
+                .enumerate()
+                .all(|(i, member)| !members[..i].contains(member));
+            if !unique || threshold == 0 || threshold as usize > members.len() {
+                return Err(Error::InvalidCommittee);
+            }
+            Ok(())
+        }
+
+        #[inline]
+        fn only_committee_member(&self) -> Result<()> {
+            if !self.committee().contains(&self.env().caller()) {
+                return Err(Error::NotCommitteeMember);
+            }
+            Ok(())
+        }
+
+        /// Returns an error unless the caller holds `role` and no committee manages the fund.
+        #[inline]
+        fn only_sole_role(&self, role: Role) -> Result<()> {
+            self.only_role(role)?;
+            if self.has_committee() {
+                return Err(Error::CommitteeApprovalRequired);
+            }
+            Ok(())
+        }
+
+        /// The members of the manager committee, or none if the manager acts alone.
+        #[ink(message)]
+        pub fn committee(&self) -> Vec<AccountId> {
+            (0..self.committee_size.get().unwrap_or(0))
+                .filter_map(|index| self.committee_members.get(index))
+                .collect()
+        }
+
+        /// The number of approvals that committee proposals need.
+        #[ink(message)]
+        pub fn committee_threshold(&self) -> u32 {
+            self.committee_threshold.get().unwrap_or(0)
+        }
+
+        /// Returns the committee proposal with the given id, if it exists.
+        #[ink(message)]
+        pub fn committee_proposal(&self, id: u32) -> Option<CommitteeProposal> {
+            self.committee_proposals.get(id)
+        }
+
+        /// The number of committee proposals made.
+        #[ink(message)]
+        pub fn committee_proposal_count(&self) -> u32 {
+            self.committee_proposal_count.get().unwrap_or(0)
+        }
+
+        /// The number of current committee members that have approved a proposal.
+        #[ink(message)]
+        pub fn committee_approvals(&self, id: u32) -> u32 {
+            self.committee()
+                .iter()
+                .filter(|member| self.committee_approvals.contains((id, *member)))
+                .count() as u32
+        }
+
+        /// True if `member` has approved the committee proposal with the given id.
+        #[ink(message)]
+        pub fn has_approved(&self, id: u32, member: AccountId) -> bool {
+            self.committee_approvals.contains((id, member))
+        }
+
+        // endregion
+
+        // region: Roles
+
+        /// Grants `role` to `account`. Holders of `Admin` grant every role but `Guardian`, which
+        /// holders of `Guardian` grant.
+        #[ink(message)]
+        pub fn grant_role(&mut self, role: Role, account: AccountId) -> Result<()> {
+            self.only_role(role.admin())?;
+            let sender = self.env().caller();
+
+            if !self.has_role(role, account) {
+                self.roles.insert((role, account), &());
+                self.emit(RoleGranted {
+                    role,
+                    account,
+                    sender,
                 });
             }
 
-            Ok(dividend)
+            Ok(())
         }
 
-        /// The dividend that a specific AccountId is currently entitled to.
+        /// Revokes a granted `role` from `account`. The roles that the manager and the guardian
+        /// hold by their position can't be revoked.
         #[ink(message)]
-        pub fn calc_dividend(&self, user: AccountId) -> Balance {
-            let last_block = self.last_claimed_dividend.get(user).unwrap_or(0);
-            let user_balance = self.balance_of(user);
+        pub fn revoke_role(&mut self, role: Role, account: AccountId) -> Result<()> {
+            self.only_role(role.admin())?;
+            let sender = self.env().caller();
 
-            // Return 0 if user doesn't have any shares
-            if user_balance == 0 {
-                return 0;
+            if self.roles.contains((role, account)) {
+                self.roles.remove((role, account));
+                self.emit(RoleRevoked {
+                    role,
+                    account,
+                    sender,
+                });
+            }
+
+            Ok(())
+        }
+
+        /// True if `account` holds `role`, either by being the manager or the guardian, or by
+        /// having been granted it.
+        #[ink(message)]
+        pub fn has_role(&self, role: Role, account: AccountId) -> bool {
+            let holds_by_position = match role {
+                Role::Guardian => account == self.guardian,
+                _ => account == self.manager,
+            };
+            holds_by_position || self.roles.contains((role, account))
+        }
+
+        #[inline]
+        fn only_role(&self, role: Role) -> Result<()> {
+            if !self.has_role(role, self.env().caller()) {
+                return Err(Error::MissingRole(role));
+            }
+            Ok(())
+        }
+
+        // endregion
+
+        // region: Open-ended
+
+        /// Queues a subscription of the transferred ZTG.
+        fn subscribe(&mut self) -> Result<()> {
+            self.ensure_allowed(Action::Deal)?;
+            let window = self.open_window()?;
+            let amount = self.env().transferred_value();
+            if amount == 0 {
+                return Err(Error::ZeroAmount);
+            }
+
+            let (latest_window, subscribed) = self.window_subscriptions.get().unwrap_or((0, 0));
+            let subscribed = if latest_window == window {
+                subscribed
+            } else {
+                0
+            };
+            let cap = self.open_ended().map_or(0, |c| c.subscription_cap);
+            if subscribed + amount > cap {
+                return Err(Error::SubscriptionCapReached);
+            }
+            self.window_subscriptions
+                .set(&(window, subscribed + amount));
+
+            let user = self.env().caller();
+            let end = self.subscription_queue_end.get().unwrap_or(0);
+            self.subscription_queue.insert(end, &(user, amount));
+            self.subscription_queue_end.set(&(end + 1));
+            self.pending_subscriptions
+                .set(&(self.pending_subscriptions() + amount));
+
+            self.emit(SubscriptionRequested { user, amount });
+
+            Ok(())
+        }
+
+        /// Queues a redemption of `shares` of the caller's shares in an open-ended fund, which
+        /// is settled at the next NAV strike. The fund holds the shares in escrow until then,
+        /// and dividends issued meanwhile are split between the other shares.
+        #[ink(message)]
+        pub fn request_redemption(&mut self, shares: Balance) -> Result<()> {
+            self.ensure_allowed(Action::Deal)?;
+            self.open_window()?;
+            if shares == 0 {
+                return Err(Error::ZeroShares);
+            }
+
+            let user = self.env().caller();
+            self.transfer_from_to(&user, &self.env().account_id(), shares)?;
+            let end = self.redemption_queue_end.get().unwrap_or(0);
+            self.redemption_queue.insert(end, &(user, shares));
+            self.redemption_queue_end.set(&(end + 1));
+            self.pending_redemptions
+                .set(&(self.pending_redemptions() + shares));
+
+            self.emit(RedemptionRequested { user, shares });
+
+            Ok(())
+        }
+
+        /// Returns the index of the current dealing window, or an error if none is open.
+        fn open_window(&self) -> Result<u64> {
+            let config = self.open_ended().ok_or(Error::NotOpenEnded)?;
+            let elapsed = self
+                .env()
+                .block_timestamp()
+                .saturating_sub(self.activated_at());
+            if elapsed % config.window_interval >= config.window_length {
+                return Err(Error::WindowClosed);
+            }
+            Ok(elapsed / config.window_interval)
+        }
+
+        /// Settles the queued requests at the NAV that was just reported.
+        ///
+        /// Redemptions are settled first, in order, up to the redemption gate and the ZTG that
+        /// the fund holds. A redemption that doesn't fit is settled in part and the rest waits
+        /// for the next strike. Subscriptions are then settled in full.
+        // NOTE: potential DOS here, since every queued request is settled in one call
+        fn settle_requests(&mut self) -> Result<()> {
+            let Some(config) = self.open_ended() else {
+                return Ok(());
+            };
+            let nav = self.nav;
+            let outstanding = self.outstanding_shares();
+            if nav == 0 || outstanding == 0 {
+                return Ok(());
             }
 
-            // Find the index of the oldest unclaimed dividend
-            // TODO: implement binary search to make more efficient
-            let mut oldest_unclaimed_dividend = u32::MAX as usize;
-            for i in 0..self.dividends.len() {
-                if self.dividends[i].0 > last_block {
-                    oldest_unclaimed_dividend = i;
+            let fund = self.env().account_id();
+            let mut gate = mul_div(
+                outstanding,
+                Balance::from(config.redemption_gate_bps),
+                BASIS_POINTS,
+            )?;
+            let mut free = self
+                .env()
+                .balance()
+                .saturating_sub(self.pending_subscriptions());
+            let mut redeemed = 0;
+            let mut pending = self.pending_redemptions();
+            let mut start = self.redemption_queue_start.get().unwrap_or(0);
+            let end = self.redemption_queue_end.get().unwrap_or(0);
+            while start < end {
+                let index = start;
+                let Some((user, requested)) = self.redemption_queue.get(index) else {
+                    break;
+                };
+                let shares = requested.min(gate).min(mul_div(free, outstanding, nav)?);
+                if shares == 0 {
+                    break;
+                }
+
+                let amount = mul_div(shares, nav, outstanding)?;
+                self.move_shares(
+                    &fund,
+                    &AccountId::from([0; 32]),
+                    self.balance_of_impl(&fund),
+                    shares,
+                )?;
+                self.env()
+                    .transfer(user, amount)
+                    .map_err(|_| Error::TransferFailed)?;
+                gate -= shares;
+                free -= amount;
+                redeemed += amount;
+                pending -= shares;
+                self.emit(RedemptionSettled {
+                    user,
+                    shares,
+                    amount,
+                });
+
+                if shares < requested {
+                    self.redemption_queue
+                        .insert(index, &(user, requested - shares));
                     break;
                 }
+                self.redemption_queue.remove(index);
+                start += 1;
             }
-            if oldest_unclaimed_dividend > self.dividends.len() {
-                // If the oldest unclaimed dividend is too high, then there are no other dividends
-                return 0;
+            self.redemption_queue_start.set(&start);
+            self.pending_redemptions.set(&pending);
+
+            let mut subscribed = 0;
+            let mut start = self.subscription_queue_start.get().unwrap_or(0);
+            let end = self.subscription_queue_end.get().unwrap_or(0);
+            while start < end {
+                let index = start;
+                if let Some((user, amount)) = self.subscription_queue.get(index) {
+                    let shares = mul_div(amount, outstanding, nav)?;
+                    self.issue_shares(&user, shares)?;
+                    subscribed = amount.checked_add(subscribed).ok_or(Error::Overflow)?;
+                    self.emit(SubscriptionSettled {
+                        user,
+                        amount,
+                        shares,
+                    });
+                }
+                self.subscription_queue.remove(index);
+                start += 1;
             }
+            self.subscription_queue_start.set(&start);
+            self.pending_subscriptions
+                .set(&(self.pending_subscriptions() - subscribed));
+
+            self.nav = nav
+                .checked_add(subscribed)
+                .ok_or(Error::Overflow)?
+                .saturating_sub(redeemed);
+
+            Ok(())
+        }
 
-            // Find the sum of the dividends to give out since the user last received money
-            // TODO: implement binary search to make more efficient
-            let mut sum = 0;
-            for i in oldest_unclaimed_dividend..self.dividends.len() {
-                sum += self.dividends[i].1;
+        /// Gives `value` shares to `to`, first from the unissued shares of the zero address and
+        /// then by minting new ones.
+        fn issue_shares(&mut self, to: &AccountId, value: Balance) -> Result<()> {
+            let zero = AccountId::from([0; 32]);
+            let unissued = self.balance_of_impl(&zero).min(value);
+            if unissued > 0 {
+                self.transfer_from_to(&zero, to, unissued)?;
             }
+            if value > unissued {
+                self.mint(to, value - unissued);
+            }
+            Ok(())
+        }
+
+        /// Refunds every queued subscription and returns the shares of every queued redemption,
+        /// so that nothing is left waiting once dealing stops.
+        fn cancel_requests(&mut self) -> Result<()> {
+            let end = self.subscription_queue_end.get().unwrap_or(0);
+            for index in self.subscription_queue_start.get().unwrap_or(0)..end {
+                if let Some((user, amount)) = self.subscription_queue.get(index) {
+                    self.env()
+                        .transfer(user, amount)
+                        .map_err(|_| Error::TransferFailed)?;
+                    self.emit(Refunded { user, amount });
+                }
+                self.subscription_queue.remove(index);
+            }
+            self.subscription_queue_start.set(&end);
+            self.pending_subscriptions.set(&0);
+
+            let fund = self.env().account_id();
+            let end = self.redemption_queue_end.get().unwrap_or(0);
+            for index in self.redemption_queue_start.get().unwrap_or(0)..end {
+                if let Some((user, shares)) = self.redemption_queue.get(index) {
+                    self.move_shares(&fund, &user, self.balance_of_impl(&fund), shares)?;
+                }
+                self.redemption_queue.remove(index);
+            }
+            self.redemption_queue_start.set(&end);
+            self.pending_redemptions.set(&0);
+
+            Ok(())
+        }
+
+        /// The dealing terms of the fund, if it is open-ended.
+        #[ink(message)]
+        pub fn open_ended(&self) -> Option<OpenEndedConfig> {
+            self.open_ended.get()
+        }
+
+        /// True if an open-ended fund currently accepts subscriptions and redemptions.
+        #[ink(message)]
+        pub fn is_window_open(&self) -> bool {
+            self.state().allows(Action::Deal) && self.open_window().is_ok()
+        }
+
+        /// The queued subscriptions as (user, ZTG), in the order they are settled.
+        #[ink(message)]
+        pub fn subscription_requests(&self) -> Vec<(AccountId, Balance)> {
+            let start = self.subscription_queue_start.get().unwrap_or(0);
+            (start..self.subscription_queue_end.get().unwrap_or(0))
+                .filter_map(|index| self.subscription_queue.get(index))
+                .collect()
+        }
+
+        /// The queued redemptions as (user, shares), in the order they are settled.
+        #[ink(message)]
+        pub fn redemption_requests(&self) -> Vec<(AccountId, Balance)> {
+            let start = self.redemption_queue_start.get().unwrap_or(0);
+            (start..self.redemption_queue_end.get().unwrap_or(0))
+                .filter_map(|index| self.redemption_queue.get(index))
+                .collect()
+        }
+
+        /// The ZTG of queued subscriptions.
+        #[ink(message)]
+        pub fn pending_subscriptions(&self) -> Balance {
+            self.pending_subscriptions.get().unwrap_or(0)
+        }
+
+        /// The shares of queued redemptions.
+        #[ink(message)]
+        pub fn pending_redemptions(&self) -> Balance {
+            self.pending_redemptions.get().unwrap_or(0)
+        }
+
+        // endregion
+
+        // region: Fund Management
+
+        /// Allows the manager to send a call into the Swaps pallet.
+        #[ink(message)]
+        pub fn swap_call(&mut self, call: SwapsCall) -> Result<()> {
+            self.only_sole_role(Role::Trader)?;
+            self.execute_or_queue(ManagerCall::Swap(call))
+        }
+
+        /// Allows the manager to send a call into the PredictionMarkets pallet.
+        #[ink(message)]
+        pub fn prediction_market_call(&mut self, call: PredictionMarketsCall) -> Result<()> {
+            self.only_sole_role(Role::Trader)?;
+            self.execute_or_queue(ManagerCall::PredictionMarket(call))
+        }
+
+        /// Returns an error if `call` can't be sent in the fund's current state.
+        fn ensure_call_allowed(&mut self, call: &ManagerCall) -> Result<()> {
+            let (action, is_exit) = match call {
+                ManagerCall::Swap(call) => (Action::SwapCall, call.is_exit()),
+                ManagerCall::PredictionMarket(call) => {
+                    (Action::PredictionMarketCall, call.is_exit())
+                }
+                ManagerCall::IssueDividend(..) => (Action::IssueDividend, false),
+            };
+            self.ensure_allowed(action)?;
+            if self.state == FundState::Liquidating && !is_exit {
+                return Err(Error::OnlyExitsWhileLiquidating);
+            }
+            Ok(())
+        }
+
+        /// The runtime call that sends `call`, and its kind.
+        fn runtime_call(&self, call: ManagerCall) -> (RuntimeCall, RuntimeCallKind) {
+            match call {
+                ManagerCall::Swap(call) => (RuntimeCall::Swaps(call), RuntimeCallKind::Swap),
+                ManagerCall::PredictionMarket(call) => (
+                    RuntimeCall::PredictionMarkets(call),
+                    RuntimeCallKind::PredictionMarket,
+                ),
+                // Dividends are sent to the dividend wallet
+                ManagerCall::IssueDividend(asset, amount) => (
+                    RuntimeCall::AssetManager(AssetManagerCall::Transfer {
+                        dest: self.dividend_wallet.to_account_id().into(),
+                        currency_id: asset,
+                        amount,
+                    }),
+                    RuntimeCallKind::DividendTransfer,
+                ),
+            }
+        }
+
+        /// Sends `call` and records its effects on the fund.
+        fn execute_call(&mut self, call: ManagerCall) -> Result<()> {
+            let dividend = match &call {
+                ManagerCall::IssueDividend(asset, amount) => Some((asset.clone(), *amount)),
+                _ => None,
+            };
+
+            let (call, kind) = self.runtime_call(call);
+            self.check_risk_limits(&call)?;
+            self.call_runtime(&call, kind)?;
+            self.record_exposure(&call);
+            self.record_positions(&call);
+            if let Some((asset, amount)) = dividend {
+                self.record_dividend(asset, amount);
+            }
+
+            Ok(())
+        }
+
+        /// Dispatches `call`, tagging any failure with the kind of call.
+        fn call_runtime(&self, call: &RuntimeCall, kind: RuntimeCallKind) -> Result<()> {
+            self.env()
+                .call_runtime(call)
+                .map_err(|e| Error::CallRuntimeFailed {
+                    call: kind,
+                    reason: e.into(),
+                })
+        }
+
+        /// Updates the registry of positions after `call` succeeded.
+        fn record_positions(&mut self, call: &RuntimeCall) {
+            match call {
+                RuntimeCall::Swaps(SwapsCall::PoolJoin {
+                    pool_id,
+                    pool_amount,
+                    ..
+                }) => {
+                    let shares = self.pool_shares.get(pool_id).unwrap_or(0);
+                    self.pool_shares.insert(pool_id, &(shares + pool_amount));
+                    self.open_position(Position::Pool(*pool_id));
+                }
+                RuntimeCall::Swaps(SwapsCall::PoolExit {
+                    pool_id,
+                    pool_amount,
+                    ..
+                }) => {
+                    let shares = self
+                        .pool_shares
+                        .get(pool_id)
+                        .unwrap_or(0)
+                        .saturating_sub(*pool_amount);
+                    self.pool_shares.insert(pool_id, &shares);
+                    if shares == 0 {
+                        self.close_positions(|p| p == &Position::Pool(*pool_id));
+                    }
+                }
+                RuntimeCall::Swaps(
+                    SwapsCall::SwapExactAmountIn { asset_out, .. }
+                    | SwapsCall::SwapExactAmountOut { asset_out, .. },
+                ) => {
+                    if let Some(market_id) = asset_out.market_id() {
+                        self.open_position(Position::Market(market_id));
+                    }
+                    if asset_out != &ZeitgeistAsset::Ztg {
+                        self.open_position(Position::Asset(asset_out.clone()));
+                    }
+                }
+                RuntimeCall::PredictionMarkets(PredictionMarketsCall::BuyCompleteSet {
+                    market_id,
+                    amount,
+                }) => {
+                    self.complete_sets
+                        .insert(market_id, &(self.complete_sets(*market_id) + amount));
+                    self.open_position(Position::Market(*market_id));
+                }
+                RuntimeCall::PredictionMarkets(PredictionMarketsCall::SellCompleteSet {
+                    market_id,
+                    amount,
+                }) => {
+                    let sets = self.complete_sets(*market_id).saturating_sub(*amount);
+                    self.complete_sets.insert(market_id, &sets);
+                    self.close_market_if_empty(*market_id);
+                }
+                // Redeeming leaves the fund with nothing in the resolved market
+                RuntimeCall::PredictionMarkets(PredictionMarketsCall::RedeemShares {
+                    market_id,
+                }) => {
+                    self.complete_sets.remove(market_id);
+                    for position in self.positions() {
+                        if let Position::Asset(asset @ ZeitgeistAsset::CategoricalOutcome(id, _)) =
+                            position
+                        {
+                            if id == *market_id {
+                                self.asset_holdings.remove(asset);
+                            }
+                        }
+                    }
+                    self.close_positions(|p| match p {
+                        Position::Market(id)
+                        | Position::Asset(ZeitgeistAsset::CategoricalOutcome(id, _)) => {
+                            id == market_id
+                        }
+                        _ => false,
+                    });
+                }
+                _ => {}
+            }
+            self.record_holdings(call);
+        }
+
+        /// Updates the recorded holdings of outcome assets and pool shares after `call`
+        /// succeeded. Amounts that a call only bounds are recorded so that the holdings stay
+        /// lower bounds of what the fund holds.
+        fn record_holdings(&mut self, call: &RuntimeCall) {
+            match call {
+                RuntimeCall::Swaps(SwapsCall::SwapExactAmountIn {
+                    asset_in,
+                    asset_amount_in,
+                    asset_out,
+                    min_asset_amount_out,
+                    ..
+                }) => {
+                    self.remove_holding(asset_in, *asset_amount_in);
+                    self.add_holding(asset_out, min_asset_amount_out.unwrap_or(0));
+                }
+                RuntimeCall::Swaps(SwapsCall::SwapExactAmountOut {
+                    asset_in,
+                    max_asset_amount_in,
+                    asset_out,
+                    asset_amount_out,
+                    ..
+                }) => {
+                    self.remove_holding(asset_in, max_asset_amount_in.unwrap_or(Balance::MAX));
+                    self.add_holding(asset_out, *asset_amount_out);
+                }
+                RuntimeCall::AssetManager(AssetManagerCall::Transfer {
+                    currency_id,
+                    amount,
+                    ..
+                }) => {
+                    self.remove_holding(currency_id, *amount);
+                }
+                _ => {}
+            }
+        }
+
+        fn add_holding(&mut self, asset: &ZeitgeistAsset, amount: Balance) {
+            match asset {
+                ZeitgeistAsset::Ztg => {}
+                ZeitgeistAsset::PoolShare(pool_id) => {
+                    self.pool_shares
+                        .insert(pool_id, &(self.pool_shares(*pool_id) + amount));
+                }
+                asset => {
+                    self.asset_holdings
+                        .insert(asset, &(self.asset_holdings(asset.clone()) + amount));
+                }
+            }
+        }
+
+        /// Removes up to `amount` of `asset` from the holdings. An outcome asset that the fund
+        /// holds less of must have come from complete sets, which are broken up for the rest.
+        fn remove_holding(&mut self, asset: &ZeitgeistAsset, amount: Balance) {
+            match asset {
+                ZeitgeistAsset::Ztg => {}
+                ZeitgeistAsset::PoolShare(pool_id) => {
+                    let shares = self.pool_shares(*pool_id).saturating_sub(amount);
+                    self.pool_shares.insert(pool_id, &shares);
+                    if shares == 0 {
+                        self.close_positions(|p| p == &Position::Pool(*pool_id));
+                    }
+                }
+                asset => {
+                    let holding = self.asset_holdings(asset.clone());
+                    // A holding that was never bounded from below stays open
+                    if holding > 0 && amount >= holding {
+                        self.asset_holdings.remove(asset);
+                        self.close_positions(|p| p == &Position::Asset(asset.clone()));
+                    } else {
+                        self.asset_holdings
+                            .insert(asset, &holding.saturating_sub(amount));
+                    }
+                    if let Some(market_id) = asset.market_id() {
+                        if amount > holding {
+                            let sets = self
+                                .complete_sets(market_id)
+                                .saturating_sub(amount - holding);
+                            self.complete_sets.insert(market_id, &sets);
+                        }
+                        self.close_market_if_empty(market_id);
+                    }
+                }
+            }
+        }
+
+        /// Adds `position` to the registry if the fund does not hold it yet.
+        fn open_position(&mut self, position: Position) {
+            if self.add_position(&position) {
+                self.emit(PositionOpened { position });
+            }
+        }
+
+        /// Appends `position` to `position_list` unless it is already there, returning whether
+        /// it was added.
+        fn add_position(&mut self, position: &Position) -> bool {
+            if self.position_index.contains(position) {
+                return false;
+            }
+            let count = self.position_count.get().unwrap_or(0);
+            self.position_list.insert(count, position);
+            self.position_index.insert(position, &count);
+            self.position_count.set(&(count + 1));
+            true
+        }
+
+        /// Removes every position that matches `filter` from the registry, moving the last
+        /// position into each freed index.
+        fn close_positions(&mut self, filter: impl Fn(&Position) -> bool) {
+            let mut count = self.position_count.get().unwrap_or(0);
+            let mut index = 0;
+            while index < count {
+                let Some(position) = self.position_list.get(index) else {
+                    break;
+                };
+                if !filter(&position) {
+                    index += 1;
+                    continue;
+                }
+
+                count -= 1;
+                if index < count {
+                    if let Some(last) = self.position_list.get(count) {
+                        self.position_list.insert(index, &last);
+                        self.position_index.insert(&last, &index);
+                    }
+                }
+                self.position_list.remove(count);
+                self.position_index.remove(&position);
+                self.emit(PositionClosed { position });
+            }
+            self.position_count.set(&count);
+        }
+
+        /// Closes the position in `market_id` once the fund holds no complete sets or outcome
+        /// assets of it and has no exposure left in it.
+        fn close_market_if_empty(&mut self, market_id: u128) {
+            let holds_outcomes = self.positions().iter().any(
+                |p| matches!(p, Position::Asset(asset) if asset.market_id() == Some(market_id)),
+            );
+            if !holds_outcomes
+                && self.complete_sets(market_id) == 0
+                && self.market_exposure(market_id) == 0
+            {
+                self.close_positions(|p| p == &Position::Market(market_id));
+            }
+        }
+
+        /// Every market, pool and outcome asset position that the fund holds.
+        #[ink(message)]
+        pub fn positions(&self) -> Vec<Position> {
+            (0..self.position_count.get().unwrap_or(0))
+                .filter_map(|index| self.position_list.get(index))
+                .collect()
+        }
+
+        /// The markets that the fund holds positions in.
+        #[ink(message)]
+        pub fn markets(&self) -> Vec<u128> {
+            self.positions()
+                .into_iter()
+                .filter_map(|p| match p {
+                    Position::Market(market_id) => Some(market_id),
+                    _ => None,
+                })
+                .collect()
+        }
+
+        /// The pool shares that the fund holds in `pool_id`.
+        #[ink(message)]
+        pub fn pool_shares(&self, pool_id: u128) -> Balance {
+            self.pool_shares.get(pool_id).unwrap_or(0)
+        }
+
+        /// The amount of the outcome asset `asset` that the fund is known to hold.
+        #[ink(message)]
+        pub fn asset_holdings(&self, asset: ZeitgeistAsset) -> Balance {
+            self.asset_holdings.get(asset).unwrap_or(0)
+        }
+
+        /// The complete sets of `market_id` that the fund is known to hold.
+        #[ink(message)]
+        pub fn complete_sets(&self, market_id: u128) -> Balance {
+            self.complete_sets.get(market_id).unwrap_or(0)
+        }
+
+        /// Checks that `call` stays within the allow-lists and exposure limits. Exits are always
+        /// allowed, so that the manager can unwind positions that are no longer allowed.
+        fn check_risk_limits(&self, call: &RuntimeCall) -> Result<()> {
+            if call.is_exit() {
+                return Ok(());
+            }
+
+            match call {
+                RuntimeCall::Swaps(SwapsCall::PoolJoin {
+                    pool_id,
+                    max_assets_in,
+                    ..
+                }) => {
+                    self.ensure_pool_allowed(*pool_id)?;
+                    self.ensure_exposure(None, max_assets_in.iter().sum())
+                }
+                RuntimeCall::Swaps(
+                    SwapsCall::SwapExactAmountIn {
+                        pool_id, asset_out, ..
+                    }
+                    | SwapsCall::SwapExactAmountOut {
+                        pool_id, asset_out, ..
+                    },
+                ) => {
+                    self.ensure_pool_allowed(*pool_id)?;
+                    if let Some(market_id) = asset_out.market_id() {
+                        self.ensure_market_allowed(market_id)?;
+                    }
+                    self.ensure_exposure(asset_out.market_id(), self.ztg_in(call))
+                }
+                RuntimeCall::PredictionMarkets(PredictionMarketsCall::BuyCompleteSet {
+                    market_id,
+                    amount,
+                }) => {
+                    self.ensure_market_allowed(*market_id)?;
+                    self.ensure_exposure(Some(*market_id), *amount)
+                }
+                _ => Ok(()),
+            }
+        }
+
+        fn ensure_market_allowed(&self, market_id: u128) -> Result<()> {
+            if !self.risk_limits.allowed_markets.contains(&market_id) {
+                return Err(Error::MarketNotAllowed);
+            }
+            Ok(())
+        }
+
+        fn ensure_pool_allowed(&self, pool_id: u128) -> Result<()> {
+            if !self.risk_limits.allowed_pools.contains(&pool_id) {
+                return Err(Error::PoolNotAllowed);
+            }
+            Ok(())
+        }
+
+        /// Checks that putting `amount` more ZTG into `market_id` stays within the exposure
+        /// limits, which are relative to the fund's size.
+        fn ensure_exposure(&self, market_id: Option<u128>, amount: Balance) -> Result<()> {
+            let size = self.fund_size();
+            if let Some(market_id) = market_id {
+                let exposure = self.market_exposure(market_id) + amount;
+                if exposure * BASIS_POINTS
+                    > size * Balance::from(self.risk_limits.max_market_exposure_bps)
+                {
+                    return Err(Error::MarketExposureTooHigh);
+                }
+            }
+
+            let exposure = self.total_exposure + amount;
+            if exposure * BASIS_POINTS
+                > size * Balance::from(self.risk_limits.max_total_exposure_bps)
+            {
+                return Err(Error::TotalExposureTooHigh);
+            }
+
+            Ok(())
+        }
+
+        /// The size of the fund in ZTG that exposures are measured against. Subscriptions and
+        /// redemptions of open-ended funds change it, so it is the last reported NAV, or the
+        /// outstanding shares, which were issued 1:1 for ZTG, before any NAV is reported.
+        fn fund_size(&self) -> Balance {
+            if self.nav > 0 {
+                self.nav
+            } else {
+                self.outstanding_shares()
+            }
+        }
+
+        /// The most ZTG that `call` can take from the fund. A swap without a maximum input
+        /// counts as the whole fund.
+        fn ztg_in(&self, call: &RuntimeCall) -> Balance {
+            match call {
+                RuntimeCall::Swaps(SwapsCall::PoolJoin { max_assets_in, .. }) => {
+                    max_assets_in.iter().sum()
+                }
+                RuntimeCall::Swaps(SwapsCall::SwapExactAmountIn {
+                    asset_in: ZeitgeistAsset::Ztg,
+                    asset_amount_in,
+                    ..
+                }) => *asset_amount_in,
+                RuntimeCall::Swaps(SwapsCall::SwapExactAmountOut {
+                    asset_in: ZeitgeistAsset::Ztg,
+                    max_asset_amount_in,
+                    ..
+                }) => max_asset_amount_in.unwrap_or_else(|| self.fund_size()),
+                RuntimeCall::PredictionMarkets(PredictionMarketsCall::BuyCompleteSet {
+                    amount,
+                    ..
+                }) => *amount,
+                _ => 0,
+            }
+        }
+
+        /// Updates the fund's exposures after `call` succeeded. Must be called before the
+        /// positions are recorded, since pool exits release exposure pro-rata to pool shares.
+        fn record_exposure(&mut self, call: &RuntimeCall) {
+            let ztg_in = self.ztg_in(call);
+            match call {
+                RuntimeCall::Swaps(SwapsCall::PoolJoin { pool_id, .. }) => {
+                    let exposure = self.pool_exposure.get(pool_id).unwrap_or(0);
+                    self.pool_exposure.insert(pool_id, &(exposure + ztg_in));
+                    self.total_exposure += ztg_in;
+                }
+                RuntimeCall::Swaps(SwapsCall::PoolExit {
+                    pool_id,
+                    pool_amount,
+                    ..
+                }) => {
+                    let exposure = self.pool_exposure.get(pool_id).unwrap_or(0);
+                    let shares = self.pool_shares(*pool_id);
+                    let released = if *pool_amount >= shares {
+                        exposure
+                    } else {
+                        exposure * pool_amount / shares
+                    };
+                    self.pool_exposure.insert(pool_id, &(exposure - released));
+                    self.total_exposure = self.total_exposure.saturating_sub(released);
+                }
+                RuntimeCall::Swaps(
+                    SwapsCall::SwapExactAmountIn {
+                        asset_in,
+                        asset_out,
+                        ..
+                    }
+                    | SwapsCall::SwapExactAmountOut {
+                        asset_in,
+                        asset_out,
+                        ..
+                    },
+                ) => {
+                    if let Some(market_id) = asset_out.market_id() {
+                        self.add_market_exposure(market_id, ztg_in);
+                    } else {
+                        self.total_exposure += ztg_in;
+                    }
+
+                    // Only the guaranteed ZTG out is released
+                    let ztg_out = match call {
+                        RuntimeCall::Swaps(SwapsCall::SwapExactAmountIn {
+                            asset_out: ZeitgeistAsset::Ztg,
+                            min_asset_amount_out,
+                            ..
+                        }) => min_asset_amount_out.unwrap_or(0),
+                        RuntimeCall::Swaps(SwapsCall::SwapExactAmountOut {
+                            asset_out: ZeitgeistAsset::Ztg,
+                            asset_amount_out,
+                            ..
+                        }) => *asset_amount_out,
+                        _ => 0,
+                    };
+                    if let Some(market_id) = asset_in.market_id() {
+                        self.release_market_exposure(market_id, ztg_out);
+                    }
+                }
+                RuntimeCall::PredictionMarkets(PredictionMarketsCall::BuyCompleteSet {
+                    market_id,
+                    ..
+                }) => {
+                    self.add_market_exposure(*market_id, ztg_in);
+                }
+                RuntimeCall::PredictionMarkets(PredictionMarketsCall::SellCompleteSet {
+                    market_id,
+                    amount,
+                }) => {
+                    self.release_market_exposure(*market_id, *amount);
+                }
+                RuntimeCall::PredictionMarkets(PredictionMarketsCall::RedeemShares {
+                    market_id,
+                }) => {
+                    self.release_market_exposure(*market_id, Balance::MAX);
+                }
+                _ => {}
+            }
+        }
+
+        fn add_market_exposure(&mut self, market_id: u128, amount: Balance) {
+            let exposure = self.market_exposure(market_id);
+            self.market_exposure.insert(market_id, &(exposure + amount));
+            self.total_exposure += amount;
+        }
+
+        fn release_market_exposure(&mut self, market_id: u128, amount: Balance) {
+            let exposure = self.market_exposure(market_id);
+            let released = exposure.min(amount);
+            self.market_exposure
+                .insert(market_id, &(exposure - released));
+            self.total_exposure = self.total_exposure.saturating_sub(released);
+        }
+
+        /// The mandate that the manager's trades must stay within.
+        #[ink(message)]
+        pub fn risk_limits(&self) -> RiskLimits {
+            self.risk_limits.clone()
+        }
+
+        /// The terms on which large manager actions are queued behind the timelock.
+        #[ink(message)]
+        pub fn timelock(&self) -> TimelockConfig {
+            self.timelock.get().unwrap_or_default()
+        }
+
+        /// The ZTG that the fund has put into `market_id`.
+        #[ink(message)]
+        pub fn market_exposure(&self, market_id: u128) -> Balance {
+            self.market_exposure.get(market_id).unwrap_or(0)
+        }
+
+        /// The ZTG that the fund has put into markets and pools in total.
+        #[ink(message)]
+        pub fn total_exposure(&self) -> Balance {
+            self.total_exposure
+        }
+
+        // endregion
+
+        // region: Timelock
+
+        /// Sends `call`, or queues it if it can move more ZTG than the timelock threshold.
+        fn execute_or_queue(&mut self, call: ManagerCall) -> Result<()> {
+            self.ensure_call_allowed(&call)?;
+            let ztg = self.call_ztg(&call);
+            let timelock = self.timelock();
+            if ztg <= timelock.threshold {
+                return self.execute_call(call);
+            }
+
+            // Calls outside of the mandate are rejected right away
+            let (runtime_call, _) = self.runtime_call(call.clone());
+            self.check_risk_limits(&runtime_call)?;
+
+            let id = self.queued_action_count();
+            let now = self.env().block_timestamp();
+            let eta = now + timelock.delay;
+            self.queued_actions.insert(
+                id,
+                &QueuedAction {
+                    call: call.clone(),
+                    ztg,
+                    eta,
+                    snapshot: now.saturating_sub(1),
+                    vetoes: 0,
+                    status: ActionStatus::Queued,
+                },
+            );
+            self.queued_action_count.set(&(id + 1));
+
+            self.emit(ActionQueued { id, call, ztg, eta });
+
+            Ok(())
+        }
+
+        /// The most ZTG that `call` can move out of the fund.
+        fn call_ztg(&self, call: &ManagerCall) -> Balance {
+            match call {
+                ManagerCall::IssueDividend(ZeitgeistAsset::Ztg, amount) => *amount,
+                ManagerCall::IssueDividend(..) => 0,
+                call => self.ztg_in(&self.runtime_call(call.clone()).0),
+            }
+        }
+
+        /// Allows holders of the role that the action needs, or any member of the manager's
+        /// committee, to execute a queued action once its timelock has passed, if it was not
+        /// vetoed. The action must still be allowed in the fund's current state and mandate.
+        #[ink(message)]
+        pub fn execute_action(&mut self, id: u32) -> Result<()> {
+            let mut action = self.queued_actions.get(id).ok_or(Error::ActionNotFound)?;
+            if !self.has_committee() {
+                self.only_role(action.call.role())?;
+            } else {
+                self.only_committee_member()?;
+            }
+            if action.status != ActionStatus::Queued {
+                return Err(Error::ActionNotQueued);
+            }
+            if self.env().block_timestamp() < action.eta {
+                return Err(Error::TimelockNotOver);
+            }
+            self.ensure_call_allowed(&action.call)?;
+
+            action.status = ActionStatus::Executed;
+            self.queued_actions.insert(id, &action);
+            self.execute_call(action.call)?;
+
+            self.emit(ActionExecuted { id });
+
+            Ok(())
+        }
+
+        /// Vetoes a queued action with the caller's shares at the time it was queued. The action
+        /// is cancelled once the vetoes hold more than `ACTION_VETO_PERCENT` of the shares.
+        #[ink(message)]
+        pub fn veto_action(&mut self, id: u32) -> Result<Balance> {
+            self.ensure_allowed(Action::Govern)?;
+            let voter = self.env().caller();
+            let mut action = self.queued_actions.get(id).ok_or(Error::ActionNotFound)?;
+            if action.status != ActionStatus::Queued {
+                return Err(Error::ActionNotQueued);
+            }
+            if self.action_vetoes.contains((id, voter)) {
+                return Err(Error::AlreadyVoted);
+            }
+
+            let weight = self.balance_of_at(voter, action.snapshot);
+            if weight == 0 {
+                return Err(Error::NoVotingPower);
+            }
+
+            action.vetoes += weight;
+            if action.vetoes * 100
+                > self.outstanding_shares_at(action.snapshot) * ACTION_VETO_PERCENT
+            {
+                action.status = ActionStatus::Vetoed;
+                self.emit(ActionVetoed {
+                    id,
+                    vetoes: action.vetoes,
+                });
+            }
+            self.queued_actions.insert(id, &action);
+            self.action_vetoes.insert((id, voter), &());
+
+            Ok(weight)
+        }
+
+        /// Returns the queued action with the given id, if it exists.
+        #[ink(message)]
+        pub fn queued_action(&self, id: u32) -> Option<QueuedAction> {
+            self.queued_actions.get(id)
+        }
+
+        /// The number of actions queued.
+        #[ink(message)]
+        pub fn queued_action_count(&self) -> u32 {
+            self.queued_action_count.get().unwrap_or(0)
+        }
+
+        /// Returns up to `limit` queued actions with their ids, starting at the id `from`.
+        #[ink(message)]
+        pub fn queued_actions(&self, from: u32, limit: u32) -> Vec<(u32, QueuedAction)> {
+            (from..self.queued_action_count())
+                .take(limit as usize)
+                .filter_map(|id| self.queued_actions.get(id).map(|action| (id, action)))
+                .collect()
+        }
+
+        /// Returns the actions that are still queued, with their ids.
+        #[ink(message)]
+        pub fn pending_actions(&self) -> Vec<(u32, QueuedAction)> {
+            self.queued_actions(0, self.queued_action_count())
+                .into_iter()
+                .filter(|(_, action)| action.status == ActionStatus::Queued)
+                .collect()
+        }
+
+        /// True if `voter` has vetoed the queued action with the given id.
+        #[ink(message)]
+        pub fn has_vetoed(&self, id: u32, voter: AccountId) -> bool {
+            self.action_vetoes.contains((id, voter))
+        }
+
+        // endregion
+
+        // region: Liquidation
+
+        /// Moves the fund into liquidation, after which the manager can only exit positions and
+        /// users can redeem their shares for ZTG.
+        ///
+        /// Only the manager can liquidate during the grace period after maturity. Afterwards,
+        /// anyone can.
+        #[ink(message)]
+        pub fn liquidate(&mut self) -> Result<()> {
+            self.ensure_allowed(Action::Liquidate)?;
+
+            let caller = self.env().caller();
+            let timestamp = self.env().block_timestamp();
+            if timestamp < self.maturity {
+                return Err(Error::MaturityNotReached);
+            }
+            if caller != self.manager
+                && timestamp < self.maturity.saturating_add(LIQUIDATION_GRACE_PERIOD)
+            {
+                return Err(Error::GracePeriodNotOver);
+            }
+
+            // Charge the fees earned up until liquidation
+            self.mint_fees();
+            self.cancel_requests()?;
+
+            self.set_state(FundState::Liquidating);
+            self.emit(LiquidationStarted { caller, timestamp });
+
+            Ok(())
+        }
+
+        /// Burns `shares` of the caller's shares back to the zero address in return for their
+        /// pro-rata share of the fund's free ZTG.
+        ///
+        /// On success a `Redeemed` event is emitted.
+        #[ink(message)]
+        pub fn redeem(&mut self, shares: Balance) -> Result<Balance> {
+            self.ensure_allowed(Action::Redeem)?;
+            if shares == 0 {
+                return Err(Error::ZeroShares);
+            }
+
+            let caller = self.env().caller();
+            let balance = self.balance_of_impl(&caller);
+            if balance < shares {
+                return Err(Error::InsufficientBalance);
+            }
+
+            // Calculate before burning, since burning changes the outstanding shares
+            let amount = self.env().balance() * shares / self.outstanding_shares();
+
+            // Burn shares, ignoring the manager lock since it only lasts until liquidation
+            self.move_shares(&caller, &AccountId::from([0; 32]), balance, shares)?;
+
+            self.env()
+                .transfer(caller, amount)
+                .map_err(|_| Error::TransferFailed)?;
+
+            self.emit(Redeemed {
+                user: caller,
+                shares,
+                amount,
+            });
+
+            if self.outstanding_shares() == 0 {
+                self.set_state(FundState::Closed);
+            }
+
+            Ok(amount)
+        }
+
+        /// The shares that are not held by the zero address.
+        #[inline]
+        fn outstanding_shares(&self) -> Balance {
+            self.total_supply - self.balance_of_impl(&AccountId::from([0; 32]))
+        }
+
+        /// The timestamp after which the fund can be liquidated.
+        #[ink(message)]
+        pub fn maturity(&self) -> Timestamp {
+            self.maturity
+        }
+
+        // endregion
+
+        // region: In-kind Redemption
+
+        /// Burns `shares` of the caller's shares back to the zero address in return for their
+        /// pro-rata slice of every outcome asset, pool share and ZTG that the fund holds.
+        /// Complete sets are sold back into ZTG first, since each one is worth exactly one ZTG.
+        ///
+        /// The fund can't query its balances of other assets, so it pays out of the holdings
+        /// recorded from its calls, which are lower bounds. See `redeemable_in_kind()`.
+        ///
+        /// On success a `RedeemedInKind` event is emitted.
+        #[ink(message)]
+        pub fn redeem_in_kind(&mut self, shares: Balance) -> Result<Balance> {
+            self.ensure_allowed(Action::RedeemInKind)?;
+            if shares == 0 {
+                return Err(Error::ZeroShares);
+            }
+
+            let caller = self.env().caller();
+            let balance = self.balance_of_impl(&caller);
+            if balance < shares {
+                return Err(Error::InsufficientBalance);
+            }
+            // The manager's shares stay locked until they vest or the fund liquidates
+            if caller == self.manager_vesting_account()
+                && shares > balance - self.vesting_locked_shares()
+            {
+                return Err(Error::ManagerSharesAreLocked);
+            }
+
+            // Calculate before burning, since burning changes the outstanding shares
+            let (ztg, sets, assets) = self.in_kind_slice(shares);
+            self.release_exposure(shares);
+            self.move_shares(&caller, &AccountId::from([0; 32]), balance, shares)?;
+
+            for (market_id, amount) in sets {
+                let call = RuntimeCall::PredictionMarkets(PredictionMarketsCall::SellCompleteSet {
+                    market_id,
+                    amount,
+                });
+                self.call_runtime(&call, RuntimeCallKind::PredictionMarket)?;
+                self.record_positions(&call);
+            }
+            for (asset, amount) in &assets {
+                let call = RuntimeCall::AssetManager(AssetManagerCall::Transfer {
+                    dest: caller.into(),
+                    currency_id: asset.clone(),
+                    amount: *amount,
+                });
+                self.call_runtime(&call, RuntimeCallKind::InKindTransfer)?;
+                self.record_holdings(&call);
+            }
+            self.env()
+                .transfer(caller, ztg)
+                .map_err(|_| Error::TransferFailed)?;
+
+            self.emit(RedeemedInKind {
+                user: caller,
+                shares,
+                ztg,
+                assets,
+            });
+
+            if self.state == FundState::Liquidating && self.outstanding_shares() == 0 {
+                self.set_state(FundState::Closed);
+            }
+
+            Ok(ztg)
+        }
+
+        /// The ZTG, including the value of complete sets, and the other assets that redeeming
+        /// `shares` in kind pays out now.
+        #[ink(message)]
+        pub fn redeemable_in_kind(
+            &self,
+            shares: Balance,
+        ) -> (Balance, Vec<(ZeitgeistAsset, Balance)>) {
+            let (ztg, _, assets) = self.in_kind_slice(shares);
+            (ztg, assets)
+        }
+
+        /// The slice of the fund that `shares` are worth, as the ZTG to pay out, the complete
+        /// sets to sell for it and the other assets to transfer.
+        #[allow(clippy::type_complexity)]
+        fn in_kind_slice(
+            &self,
+            shares: Balance,
+        ) -> (
+            Balance,
+            Vec<(u128, Balance)>,
+            Vec<(ZeitgeistAsset, Balance)>,
+        ) {
+            let outstanding = self.outstanding_shares();
+            if outstanding == 0 {
+                return (0, Vec::new(), Vec::new());
+            }
+            let slice = |amount: Balance| amount * shares / outstanding;
+
+            // ZTG of queued subscriptions belongs to the subscribers
+            let mut ztg = slice(
+                self.env()
+                    .balance()
+                    .saturating_sub(self.pending_subscriptions()),
+            );
+            let mut sets = Vec::new();
+            let mut assets = Vec::new();
+            for position in &self.positions() {
+                let (asset, amount) = match position {
+                    Position::Market(market_id) => {
+                        let amount = slice(self.complete_sets(*market_id));
+                        if amount > 0 {
+                            sets.push((*market_id, amount));
+                            ztg += amount;
+                        }
+                        continue;
+                    }
+                    Position::Pool(pool_id) => (
+                        ZeitgeistAsset::PoolShare(*pool_id),
+                        slice(self.pool_shares(*pool_id)),
+                    ),
+                    Position::Asset(asset) => {
+                        (asset.clone(), slice(self.asset_holdings(asset.clone())))
+                    }
+                };
+                if amount > 0 {
+                    assets.push((asset, amount));
+                }
+            }
+
+            (ztg, sets, assets)
+        }
+
+        /// Releases the exposure that `shares` are responsible for, since their slice of the
+        /// fund's positions leaves it.
+        fn release_exposure(&mut self, shares: Balance) {
+            let outstanding = self.outstanding_shares();
+            for position in self.positions() {
+                match position {
+                    Position::Market(market_id) => {
+                        let released = self.market_exposure(market_id) * shares / outstanding;
+                        self.release_market_exposure(market_id, released);
+                        self.close_market_if_empty(market_id);
+                    }
+                    Position::Pool(pool_id) => {
+                        let exposure = self.pool_exposure.get(pool_id).unwrap_or(0);
+                        let released = exposure * shares / outstanding;
+                        self.pool_exposure.insert(pool_id, &(exposure - released));
+                        self.total_exposure = self.total_exposure.saturating_sub(released);
+                    }
+                    Position::Asset(_) => {}
+                }
+            }
+        }
+
+        // endregion
+
+        // region: Fees
+
+        /// Reports the net asset value of the fund in ZTG, which performance fees are charged
+        /// against. Only the manager can report.
+        ///
+        /// For an active open-ended fund, this is also the NAV strike that queued subscriptions
+        /// and redemptions are settled at, and its NAV oracle can report as well. The NAV must
+        /// not include the ZTG of queued subscriptions.
+        #[ink(message)]
+        pub fn report_nav(&mut self, nav: Balance) -> Result<()> {
+            let caller = self.env().caller();
+            let oracle = self.open_ended().and_then(|c| c.nav_oracle);
+            if caller != self.manager && Some(caller) != oracle {
+                return Err(Error::OnlyManagerAllowed);
+            }
+            self.ensure_allowed(Action::Fees)?;
+
+            self.nav = nav;
+            let timestamp = self.env().block_timestamp();
+            self.emit(NavReported { nav, timestamp });
+
+            if self.state == FundState::Active {
+                self.settle_requests()?;
+            }
+
+            Ok(())
+        }
+
+        /// Mints the management fee accrued so far and, if the crystallisation period has
+        /// passed, the performance fee to the fee recipient. Anyone can accrue fees.
+        ///
+        /// Returns the management and performance fee shares minted.
+        #[ink(message)]
+        pub fn accrue_fees(&mut self) -> Result<(Balance, Balance)> {
+            self.ensure_allowed(Action::Fees)?;
+            // A raise that was paused never started charging fees
+            if self.unpaused_state() != FundState::Active {
+                return Err(Error::NotAllowedInState(self.state));
+            }
+            Ok(self.mint_fees())
+        }
+
+        /// The management fee shares that have accrued but not yet been minted.
+        #[ink(message)]
+        pub fn pending_management_fee(&self) -> Balance {
+            if self.unpaused_state() != FundState::Active {
+                return 0;
+            }
+
+            let elapsed = self
+                .env()
+                .block_timestamp()
+                .saturating_sub(self.management_fee_accrued_at);
+            self.outstanding_shares()
+                * Balance::from(self.fees.management_fee_bps)
+                * Balance::from(elapsed)
+                / (BASIS_POINTS * Balance::from(YEAR))
+        }
+
+        /// The performance fee shares that would be minted if the last reported NAV were
+        /// crystallised now.
+        #[ink(message)]
+        pub fn pending_performance_fee(&self) -> Balance {
+            let outstanding = self.outstanding_shares();
+            if self.unpaused_state() != FundState::Active || self.nav == 0 || outstanding == 0 {
+                return 0;
+            }
+
+            let nav_per_share = self.nav * NAV_PRECISION / outstanding;
+            if nav_per_share <= self.high_water_mark {
+                return 0;
+            }
+
+            // The fee is a portion of the gain above the high-water mark, in ZTG
+            let gain = (nav_per_share - self.high_water_mark) * outstanding / NAV_PRECISION;
+            let fee = gain * Balance::from(self.fees.performance_fee_bps) / BASIS_POINTS;
+
+            // Mint shares that are worth the fee after dilution
+            fee * outstanding / (self.nav - fee)
+        }
+
+        /// Mints accrued fees to the fee recipient.
+        fn mint_fees(&mut self) -> (Balance, Balance) {
+            let timestamp = self.env().block_timestamp();
+
+            let management_fee = self.pending_management_fee();
+            self.management_fee_accrued_at = timestamp;
+            if management_fee > 0 {
+                self.mint(&self.fees.recipient.clone(), management_fee);
+            }
+
+            let mut performance_fee = 0;
+            let crystallises_at = self
+                .performance_fee_crystallised_at
+                .saturating_add(self.fees.crystallisation_period);
+            if timestamp >= crystallises_at {
+                performance_fee = self.pending_performance_fee();
+                if performance_fee > 0 {
+                    self.mint(&self.fees.recipient.clone(), performance_fee);
+                }
+                self.performance_fee_crystallised_at = timestamp;
+
+                // The high-water mark only ever rises
+                let outstanding = self.outstanding_shares();
+                if self.nav > 0 && outstanding > 0 {
+                    let nav_per_share = self.nav * NAV_PRECISION / outstanding;
+                    self.high_water_mark = self.high_water_mark.max(nav_per_share);
+                }
+            }
+
+            if management_fee > 0 || performance_fee > 0 {
+                self.emit(FeesAccrued {
+                    recipient: self.fees.recipient,
+                    management_fee,
+                    performance_fee,
+                    timestamp,
+                });
+            }
+
+            (management_fee, performance_fee)
+        }
+
+        /// Creates `value` new shares for `to`.
+        fn mint(&mut self, to: &AccountId, value: Balance) {
+            self.settle_dividend(to);
+
+            let to_balance = self.balance_of_impl(to);
+            self.balances.insert(to, &(to_balance + value));
+            self.write_checkpoint(to, to_balance + value);
+            self.total_supply += value;
+            self.write_supply_checkpoint(self.total_supply);
+
+            self.emit(Transfer {
+                from: None,
+                to: Some(*to),
+                value,
+            });
+        }
+
+        /// The fees that the fund charges.
+        #[ink(message)]
+        pub fn fees(&self) -> FeeConfig {
+            self.fees.clone()
+        }
+
+        /// The last net asset value of the fund in ZTG reported by the manager.
+        #[ink(message)]
+        pub fn nav(&self) -> Balance {
+            self.nav
+        }
+
+        /// The highest NAV per share that performance fees have been charged against, scaled
+        /// by `NAV_PRECISION`.
+        #[ink(message)]
+        pub fn high_water_mark(&self) -> Balance {
+            self.high_water_mark
+        }
+
+        // endregion
+
+        // region: Governance
+
+        /// Proposes that `new_manager` replaces the current manager. The caller must hold
+        /// shares.
+        ///
+        /// Votes are weighted by share balances just before the proposal, so that shares cannot
+        /// be transferred to vote twice.
+        #[ink(message)]
+        pub fn propose_manager_replacement(&mut self, new_manager: AccountId) -> Result<u32> {
+            self.propose(ProposalAction::ReplaceManager(new_manager))
+        }
+
+        /// Proposes that `risk_limits` replace the fund's current risk limits. Voting works the
+        /// same way as for manager proposals.
+        #[ink(message)]
+        pub fn propose_risk_limits(&mut self, risk_limits: RiskLimits) -> Result<u32> {
+            if !risk_limits.is_valid() {
+                return Err(Error::InvalidRiskLimits);
+            }
+            self.propose(ProposalAction::SetRiskLimits(risk_limits))
+        }
+
+        /// Proposes that `timelock` replace the fund's current timelock terms. Voting works the
+        /// same way as for manager proposals.
+        #[ink(message)]
+        pub fn propose_timelock(&mut self, timelock: TimelockConfig) -> Result<u32> {
+            self.propose(ProposalAction::SetTimelock(timelock))
+        }
+
+        /// Proposes that the fund's code is replaced with the code at `code_hash`. Voting works
+        /// the same way as for manager proposals.
+        ///
+        /// The new code must be able to decode the fund's storage. If it has a newer storage
+        /// version, `migrate()` must be called after the proposal is executed.
+        #[ink(message)]
+        pub fn propose_upgrade(&mut self, code_hash: Hash) -> Result<u32> {
+            self.propose(ProposalAction::Upgrade(code_hash))
+        }
+
+        /// Proposes that the dividend wallet's code is replaced with the code at `code_hash`.
+        /// Voting works the same way as for manager proposals.
+        #[ink(message)]
+        pub fn propose_dividend_wallet_upgrade(&mut self, code_hash: Hash) -> Result<u32> {
+            self.propose(ProposalAction::UpgradeDividendWallet(code_hash))
+        }
+
+        fn propose(&mut self, action: ProposalAction) -> Result<u32> {
+            self.ensure_allowed(Action::Govern)?;
+            let proposer = self.env().caller();
+            if self.balance_of_impl(&proposer) == 0 {
+                return Err(Error::NoVotingPower);
+            }
+
+            let id = self.manager_proposal_count;
+            let snapshot = self.env().block_timestamp().saturating_sub(1);
+            self.manager_proposals.insert(
+                id,
+                &ManagerProposal {
+                    proposer,
+                    action: action.clone(),
+                    snapshot,
+                    votes: 0,
+                    quorum_reached_at: None,
+                    executed: false,
+                },
+            );
+            self.manager_proposal_count += 1;
+
+            self.emit(ManagerProposed {
+                id,
+                proposer,
+                action,
+                snapshot,
+            });
+
+            Ok(id)
+        }
+
+        /// Votes for a proposal with the caller's shares at the proposal's snapshot.
+        #[ink(message)]
+        pub fn vote_manager_replacement(&mut self, id: u32) -> Result<Balance> {
+            self.ensure_allowed(Action::Govern)?;
+            let voter = self.env().caller();
+            let mut proposal = self
+                .manager_proposals
+                .get(id)
+                .ok_or(Error::ProposalNotFound)?;
+            if proposal.executed {
+                return Err(Error::ProposalAlreadyExecuted);
+            }
+            if self.manager_votes.contains((id, voter)) {
+                return Err(Error::AlreadyVoted);
+            }
+
+            let weight = self.balance_of_at(voter, proposal.snapshot);
+            if weight == 0 {
+                return Err(Error::NoVotingPower);
+            }
+
+            proposal.votes += weight;
+            if proposal.quorum_reached_at.is_none()
+                && proposal.votes * 100
+                    > self.outstanding_shares_at(proposal.snapshot) * MANAGER_VOTE_QUORUM_PERCENT
+            {
+                proposal.quorum_reached_at = Some(self.env().block_timestamp());
+            }
+            self.manager_proposals.insert(id, &proposal);
+            self.manager_votes.insert((id, voter), &());
+
+            self.emit(ManagerVoteCast { id, voter, weight });
+
+            Ok(weight)
+        }
+
+        /// Executes a proposal once it has reached quorum and its timelock has passed. Anyone can
+        /// execute a proposal.
+        #[ink(message)]
+        pub fn execute_manager_replacement(&mut self, id: u32) -> Result<()> {
+            self.ensure_allowed(Action::Govern)?;
+            let mut proposal = self
+                .manager_proposals
+                .get(id)
+                .ok_or(Error::ProposalNotFound)?;
+            if proposal.executed {
+                return Err(Error::ProposalAlreadyExecuted);
+            }
+            let quorum_reached_at = proposal.quorum_reached_at.ok_or(Error::QuorumNotReached)?;
+            if self.env().block_timestamp() < quorum_reached_at + MANAGER_VOTE_TIMELOCK {
+                return Err(Error::TimelockNotOver);
+            }
+
+            proposal.executed = true;
+            self.manager_proposals.insert(id, &proposal);
+
+            match proposal.action {
+                ProposalAction::ReplaceManager(new_manager) => {
+                    let old_manager = self.manager;
+                    // The replaced manager's shares keep vesting, while the new manager's are free
+                    self.manager = new_manager;
+                    // A rotation proposed by the replaced manager can no longer be accepted
+                    self.pending_manager.set(&None);
+
+                    self.emit(ManagerReplaced {
+                        id,
+                        old_manager,
+                        new_manager,
+                    });
+                }
+                ProposalAction::SetRiskLimits(risk_limits) => {
+                    self.risk_limits = risk_limits.clone();
+                    self.emit(RiskLimitsUpdated { id, risk_limits });
+                }
+                ProposalAction::Upgrade(code_hash) => {
+                    self.upgrade(code_hash)?;
+                    self.emit(Upgraded {
+                        id,
+                        contract: self.env().account_id(),
+                        code_hash,
+                    });
+                }
+                ProposalAction::UpgradeDividendWallet(code_hash) => {
+                    self.upgrade_dividend_wallet(code_hash)?;
+                    self.emit(Upgraded {
+                        id,
+                        contract: self.dividend_wallet.to_account_id(),
+                        code_hash,
+                    });
+                }
+                ProposalAction::SetTimelock(timelock) => {
+                    self.timelock.set(&timelock);
+                    self.emit(TimelockUpdated { id, timelock });
+                }
+            }
+
+            Ok(())
+        }
+
+        /// Returns the manager proposal with the given id, if it exists.
+        #[ink(message)]
+        pub fn manager_proposal(&self, id: u32) -> Option<ManagerProposal> {
+            self.manager_proposals.get(id)
+        }
+
+        /// The number of manager proposals made.
+        #[ink(message)]
+        pub fn manager_proposal_count(&self) -> u32 {
+            self.manager_proposal_count
+        }
+
+        /// True if `voter` has voted on the manager proposal with the given id.
+        #[ink(message)]
+        pub fn has_voted(&self, id: u32, voter: AccountId) -> bool {
+            self.manager_votes.contains((id, voter))
+        }
+
+        // endregion
+
+        // region: Upgrades
+
+        /// Replaces the fund's code. The new code is used from the next call onwards.
+        fn upgrade(&mut self, code_hash: Hash) -> Result<()> {
+            self.env().set_code_hash(&code_hash)?;
+            Ok(())
+        }
+
+        fn upgrade_dividend_wallet(&mut self, code_hash: Hash) -> Result<()> {
+            self.dividend_wallet
+                .call_mut()
+                .upgrade(code_hash)
+                .try_invoke()
+                .map_err(|e| Error::DividendWalletCallFailed(e.into()))?
+                // The wallet could not decode the call
+                .map_err(|_| Error::DividendWalletCallFailed(EnvFailure::Decode))?
+                .map_err(Error::DividendWalletUpgradeError)
+        }
+
+        /// Migrates the fund's storage from its version to `STORAGE_VERSION`, one version at a
+        /// time. Anyone can migrate the fund after it has been upgraded, and until then it
+        /// rejects every call that is gated by its state.
+        #[ink(message)]
+        pub fn migrate(&mut self) -> Result<u32> {
+            let from = self.storage_version();
+            if from >= STORAGE_VERSION {
+                return Err(Error::AlreadyMigrated);
+            }
+
+            if from < 2 {
+                self.migrate_dividend_assets();
+            }
+            if from < 3 {
+                self.migrate_dividend_asset_list();
+                self.migrate_positions();
+            }
+            if from < 4 {
+                self.migrate_manager_lock();
+            }
+            if from < 5 {
+                self.migrate_timelock();
+            }
+
+            self.storage_version.set(&STORAGE_VERSION);
+            self.emit(Migrated {
+                from,
+                to: STORAGE_VERSION,
+            });
+
+            Ok(STORAGE_VERSION)
+        }
+
+        /// Version 2: moves the dividend assets out of the `dividend_assets` vector, which is
+        /// loaded on every call, into `dividend_asset_list`.
+        fn migrate_dividend_assets(&mut self) {
+            for asset in core::mem::take(&mut self.dividend_assets) {
+                if let Some(asset) = asset.upgrade() {
+                    self.add_dividend_asset(&asset);
+                }
+            }
+        }
+
+        /// Version 3: rewrites `dividend_asset_list`, which version 2 wrote in the legacy
+        /// encoding of `ZeitgeistAsset`, and drops the pool shares from it.
+        fn migrate_dividend_asset_list(&mut self) {
+            let key = StorageKey::key(&self.dividend_asset_list);
+            let count = self.dividend_asset_count.get().unwrap_or(0);
+            let assets: Vec<ZeitgeistAsset> = (0..count)
+                .filter_map(|index| {
+                    ink::env::get_contract_storage::<_, LegacyZeitgeistAsset>(&(key, index))
+                        .ok()
+                        .flatten()
+                })
+                .filter_map(LegacyZeitgeistAsset::upgrade)
+                .collect();
+
+            for index in 0..count {
+                self.dividend_asset_list.remove(index);
+            }
+            self.dividend_asset_count.set(&0);
+            for asset in assets {
+                self.add_dividend_asset(&asset);
+            }
+        }
+
+        /// Version 3: moves the positions out of the `positions` vector, which is loaded on every
+        /// call, into `position_list`.
+        fn migrate_positions(&mut self) {
+            for position in core::mem::take(&mut self.positions) {
+                if let Some(position) = position.upgrade() {
+                    self.add_position(&position);
+                }
+            }
+        }
+
+        /// Version 4: turns the lock on the manager's shares into a vesting schedule that only
+        /// unlocks them at liquidation, as the lock did.
+        fn migrate_manager_lock(&mut self) {
+            if self.lock_manager_shares {
+                self.manager_vesting.set(&ManagerVesting {
+                    cliff: Timestamp::MAX,
+                    duration: Timestamp::MAX,
+                });
+                self.manager_vesting_shares
+                    .set(&self.balance_of_impl(&self.manager));
+                self.lock_manager_shares = false;
+            }
+            self.manager_vesting_account.set(&self.manager);
+        }
+
+        /// Version 5: gives the fund timelock terms outside of its risk limits, which are stored
+        /// with the rest of the struct and can't grow. Funds from before the timelock get none.
+        fn migrate_timelock(&mut self) {
+            if self.timelock.get().is_none() {
+                self.timelock.set(&TimelockConfig::default());
+            }
+        }
+
+        /// The layout version of the fund's storage.
+        #[ink(message)]
+        pub fn storage_version(&self) -> u32 {
+            self.storage_version.get().unwrap_or(1)
+        }
+
+        // endregion
+
+        // region: Dividends
+
+        /// Allows the manager to issue a dividend of a specific amount of any asset that the
+        /// fund holds, such as ZTG, a foreign asset or winning outcome tokens.
+        #[ink(message)]
+        pub fn issue_dividend(&mut self, asset: ZeitgeistAsset, amount: Balance) -> Result<()> {
+            self.only_sole_role(Role::DividendIssuer)?;
+            self.execute_or_queue(ManagerCall::IssueDividend(asset, amount))
+        }
+
+        /// Adds a dividend that has been sent to the dividend wallet to the accumulator, split
+        /// between the shares that earn dividends.
+        fn record_dividend(&mut self, asset: ZeitgeistAsset, amount: Balance) {
+            if !self.dividends_per_share.contains(&asset) {
+                self.add_dividend_asset(&asset);
+            }
+            let dividends_per_share = self.dividends_per_share(asset.clone());
+            self.dividends_per_share.insert(
+                &asset,
+                &(dividends_per_share + amount * DIVIDEND_PRECISION / self.dividend_shares()),
+            );
+
+            let timestamp = self.env().block_timestamp();
+            self.emit(DividendIssued {
+                asset,
+                amount,
+                timestamp,
+            });
+        }
+
+        /// Claims a ZTG dividend for the caller.
+        #[ink(message)]
+        pub fn claim(&mut self) -> Result<Balance> {
+            self.claim_asset(ZeitgeistAsset::Ztg)
+        }
+
+        /// Claims a dividend of a specific asset for the caller.
+        #[ink(message)]
+        pub fn claim_asset(&mut self, asset: ZeitgeistAsset) -> Result<Balance> {
+            self.ensure_allowed(Action::Claim)?;
+            self.claim_dividend(self.env().caller(), asset)
+        }
+
+        /// Claims a dividend of a specific asset for a specific user
+        fn claim_dividend(&mut self, caller: AccountId, asset: ZeitgeistAsset) -> Result<Balance> {
+            // Calculate amount of dividend since last claim
+            let dividend = self.calc_asset_dividend(caller, asset.clone());
+
+            // Sets last claimed dividend
+            let block_timestamp = self.env().block_timestamp();
+            self.last_claimed_dividend.insert(caller, &block_timestamp);
+            self.reset_dividend(caller, &asset);
+
+            // Claim dividend from dividend wallet
+            if dividend > 0 {
+                self.dividend_wallet
+                    .call_mut()
+                    .distribute(caller, asset.clone().into(), dividend)
+                    .try_invoke()
+                    .map_err(|e| Error::DividendWalletCallFailed(e.into()))?
+                    // The wallet could not decode the call
+                    .map_err(|_| Error::DividendWalletCallFailed(EnvFailure::Decode))?
+                    .map_err(Error::DividendDistributionError)?;
+
+                self.emit(DividendClaimed {
+                    user: caller,
+                    asset,
+                    amount: dividend,
+                    timestamp: block_timestamp,
+                });
+            }
+
+            Ok(dividend)
+        }
+
+        /// The ZTG dividend that a specific AccountId is currently entitled to.
+        ///
+        /// Each dividend is split by the shares held when it was issued.
+        #[ink(message)]
+        pub fn calc_dividend(&self, user: AccountId) -> Balance {
+            self.calc_asset_dividend(user, ZeitgeistAsset::Ztg)
+        }
+
+        /// The dividend of a specific asset that a specific AccountId is currently entitled to.
+        #[ink(message)]
+        pub fn calc_asset_dividend(&self, user: AccountId, asset: ZeitgeistAsset) -> Balance {
+            self.dividend_credit.get((user, &asset)).unwrap_or(0)
+                + self.unsettled_dividend(&user, &asset)
+        }
+
+        /// Every asset that has been issued as a dividend.
+        #[ink(message)]
+        pub fn dividend_assets(&self) -> Vec<ZeitgeistAsset> {
+            (0..self.dividend_asset_count.get().unwrap_or(0))
+                .filter_map(|index| self.dividend_asset_list.get(index))
+                .collect()
+        }
+
+        fn add_dividend_asset(&mut self, asset: &ZeitgeistAsset) {
+            let count = self.dividend_asset_count.get().unwrap_or(0);
+            self.dividend_asset_list.insert(count, asset);
+            self.dividend_asset_count.set(&(count + 1));
+        }
+
+        /// The sum of every dividend of `asset` issued per share, scaled by
+        /// `DIVIDEND_PRECISION`.
+        #[ink(message)]
+        pub fn dividends_per_share(&self, asset: ZeitgeistAsset) -> Balance {
+            self.dividends_per_share.get(asset).unwrap_or(0)
+        }
+
+        /// The shares that earn dividends, which leaves out the burned shares of the zero address
+        /// and the shares that the fund holds in escrow for queued redemptions.
+        fn dividend_shares(&self) -> Balance {
+            self.outstanding_shares() - self.balance_of_impl(&self.env().account_id())
+        }
+
+        /// Dividends issued since the last settlement of `user`, proportional to its shares.
+        fn unsettled_dividend(&self, user: &AccountId, asset: &ZeitgeistAsset) -> Balance {
+            if user == &AccountId::from([0; 32]) || user == &self.env().account_id() {
+                return 0;
+            }
+            let debt = self.dividend_debt.get((user, asset)).unwrap_or(0);
+            let dividends_per_share = self.dividends_per_share.get(asset).unwrap_or(0);
+            self.balance_of_impl(user) * (dividends_per_share - debt) / DIVIDEND_PRECISION
+        }
+
+        /// Credits `user` with the dividends of every asset it has earned so far, so that its
+        /// balance can change without affecting them.
+        fn settle_dividend(&mut self, user: &AccountId) {
+            for asset in self.dividend_assets() {
+                let unsettled = self.unsettled_dividend(user, &asset);
+                if unsettled > 0 {
+                    let credit = self.dividend_credit.get((user, &asset)).unwrap_or(0);
+                    self.dividend_credit
+                        .insert((user, &asset), &(credit + unsettled));
+                }
+                let dividends_per_share = self.dividends_per_share(asset.clone());
+                self.dividend_debt
+                    .insert((user, &asset), &dividends_per_share);
+            }
+        }
+
+        /// Clears the dividend of `asset` owed to `user` after it has been claimed.
+        fn reset_dividend(&mut self, user: AccountId, asset: &ZeitgeistAsset) {
+            let dividends_per_share = self.dividends_per_share.get(asset).unwrap_or(0);
+            self.dividend_debt
+                .insert((user, asset), &dividends_per_share);
+            self.dividend_credit.remove((user, asset));
+        }
+
+        #[ink(message)]
+        pub fn last_dividend_claim(&self, user: AccountId) -> Timestamp {
+            self.last_claimed_dividend.get(user).unwrap_or(0)
+        }
+
+        /// The AccountId of the dividend wallet that this fund uses.
+        #[ink(message)]
+        pub fn dividend_wallet(&self) -> AccountId {
+            self.dividend_wallet.to_account_id()
+        }
+
+        /// The AccountId of the fund registered by the dividend wallet that this fund uses.
+        /// It should be the same as this smart contract. Otherwise, dividends may be handled
+        /// by a third party smart contract or address.
+        #[ink(message)]
+        pub fn dividend_wallet_fund(&self) -> AccountId {
+            self.dividend_wallet.fund()
+        }
+
+        // endregion
+
+        /// Emits an event of this contract. `self.env().emit_event()` cannot infer which contract
+        /// an event belongs to, since the dividend wallet defines events too.
+        fn emit<E>(&self, event: E)
+        where
+            E: Into<<ZeitFund as ink::reflect::ContractEventBase>::Type>,
+        {
+            ink::codegen::EmitEvent::<ZeitFund>::emit_event(self.env(), event);
+        }
+
+        #[inline]
+        fn only_manager(&self) -> Result<()> {
+            if self.env().caller() != self.manager {
+                return Err(Error::OnlyManagerAllowed);
+            }
+            Ok(())
+        }
+
+        /// Proposes to hand the manager role to `new_manager`, such as to rotate the manager's
+        /// key. The role only moves once `new_manager` accepts it. Proposing the current manager
+        /// cancels the rotation.
+        #[ink(message)]
+        pub fn propose_manager(&mut self, new_manager: AccountId) -> Result<()> {
+            self.only_manager()?;
+
+            if new_manager == self.manager {
+                self.pending_manager.set(&None);
+            } else {
+                self.pending_manager.set(&Some(new_manager));
+            }
+            self.emit(ManagerRotationProposed {
+                manager: self.manager,
+                pending_manager: new_manager,
+            });
+
+            Ok(())
+        }
+
+        /// Accepts the manager role proposed to the caller. The previous manager's shares and
+        /// their vesting move along with the role, so that they stay locked if the manager's
+        /// shares are locked.
+        /// The rotation can also be accepted while the fund is paused, so that a leaked key can be
+        /// replaced before the guardian unpauses the fund.
+        #[ink(message)]
+        pub fn accept_manager(&mut self) -> Result<()> {
+            let new_manager = self.env().caller();
+            if self.pending_manager() != Some(new_manager) {
+                return Err(Error::NotPendingManager);
+            }
+            self.ensure_allowed(Action::RotateManager)?;
+
+            let old_manager = self.manager;
+            let shares = self.balance_of_impl(&old_manager);
+            if shares > 0 {
+                self.move_shares(&old_manager, &new_manager, shares, shares)?;
+            }
+            if self.manager_vesting_account() == old_manager {
+                self.manager_vesting_account.set(&new_manager);
+            }
+            self.manager = new_manager;
+            self.pending_manager.set(&None);
+            self.emit(ManagerRotated {
+                old_manager,
+                new_manager,
+                shares,
+            });
+
+            Ok(())
+        }
+
+        /// The account that the manager proposed to hand its role to, if any.
+        #[ink(message)]
+        pub fn pending_manager(&self) -> Option<AccountId> {
+            self.pending_manager.get().flatten()
+        }
+
+        /// The current manager of the fund.
+        #[ink(message)]
+        pub fn manager(&self) -> AccountId {
+            self.manager
+        }
+
+        /// The shares that the manager owns. Should be high so that they have some skin in
+        /// the game!
+        #[ink(message)]
+        pub fn manager_shares(&self) -> u128 {
+            self.balance_of_impl(&self.manager)
+        }
+
+        /// If true, the manager cannot transfer all of their shares (and thus cannot easily rug).
+        #[ink(message)]
+        pub fn manager_is_locked(&self) -> bool {
+            self.manager == self.manager_vesting_account()
+                && self.manager_vesting().is_some_and(|vesting| {
+                    self.manager_vesting_elapsed()
+                        .is_none_or(|elapsed| elapsed < vesting.duration)
+                })
+        }
+
+        /// The schedule on which the manager's shares unlock, if they are locked at all.
+        #[ink(message)]
+        pub fn manager_vesting(&self) -> Option<ManagerVesting> {
+            self.manager_vesting.get()
+        }
+
+        /// The account whose shares vest, which is the manager unless shareholders replaced the
+        /// manager before its shares vested.
+        #[ink(message)]
+        pub fn manager_vesting_account(&self) -> AccountId {
+            self.manager_vesting_account.get().unwrap_or(self.manager)
+        }
+
+        /// The shares of the manager that are still locked. Every share is locked until
+        /// activation. The shares held at activation then vest, and every share unlocks once the
+        /// fund liquidates.
+        #[ink(message)]
+        pub fn manager_locked_shares(&self) -> Balance {
+            if self.manager != self.manager_vesting_account() {
+                return 0;
+            }
+            self.vesting_locked_shares()
+        }
+
+        /// The shares of the vesting account that are still locked, which are never more than
+        /// it holds.
+        fn vesting_locked_shares(&self) -> Balance {
+            let Some(vesting) = self.manager_vesting() else {
+                return 0;
+            };
+            let balance = self.balance_of_impl(&self.manager_vesting_account());
+            match self.manager_vesting_elapsed() {
+                Some(elapsed) => vesting
+                    .locked(self.manager_vesting_shares.get().unwrap_or(0), elapsed)
+                    .min(balance),
+                None => balance,
+            }
+        }
+
+        /// The time for which the manager's shares have vested, or none before activation.
+        /// Liquidation ends the vesting.
+        fn manager_vesting_elapsed(&self) -> Option<Timestamp> {
+            match self.unpaused_state() {
+                FundState::Active => Some(
+                    self.env()
+                        .block_timestamp()
+                        .saturating_sub(self.activated_at()),
+                ),
+                FundState::Liquidating | FundState::Closed => Some(Timestamp::MAX),
+                _ => None,
+            }
+        }
+
+        /// The shares that the manager can transfer, so that investors can see how much skin the
+        /// manager still has in the game.
+        #[ink(message)]
+        pub fn manager_unlocked_shares(&self) -> Balance {
+            self.manager_shares()
+                .saturating_sub(self.manager_locked_shares())
+        }
+    }
+
+    impl PSP22 for ZeitFund {
+        /// Returns the total token supply.
+        #[ink(message)]
+        fn total_supply(&self) -> Balance {
+            self.total_supply
+        }
+
+        /// Returns the account balance for the specified `owner`.
+        ///
+        /// Returns `0` if the account is non-existent.
+        #[ink(message)]
+        fn balance_of(&self, owner: AccountId) -> Balance {
+            self.balance_of_impl(&owner)
+        }
+
+        /// Returns the amount which `spender` is still allowed to withdraw from `owner`.
+        ///
+        /// Returns `0` if no allowance has been set.
+        #[ink(message)]
+        fn allowance(&self, owner: AccountId, spender: AccountId) -> Balance {
+            self.allowance_impl(&owner, &spender)
+        }
+
+        /// Transfers `value` amount of tokens from the caller's account to account `to`.
+        ///
+        /// On success a `Transfer` event is emitted.
+        ///
+        /// # Errors
+        ///
+        /// Returns `InsufficientBalance` error if there are not enough tokens on
+        /// the caller's account balance.
+        #[ink(message)]
+        fn transfer(
+            &mut self,
+            to: AccountId,
+            value: Balance,
+            _data: Vec<u8>,
+        ) -> core::result::Result<(), PSP22Error> {
+            let from = self.env().caller();
+            self.transfer_impl(&from, &to, value)
+        }
+
+        /// Transfers `value` tokens on the behalf of `from` to the account `to`.
+        ///
+        /// This can be used to allow a contract to transfer tokens on ones behalf and/or
+        /// to charge fees in sub-currencies, for example.
+        ///
+        /// On success a `Transfer` event is emitted, and an `Approval` event with the allowance
+        /// that is left.
+        ///
+        /// # Errors
+        ///
+        /// Returns `InsufficientAllowance` error if there are not enough tokens allowed
+        /// for the caller to withdraw from `from`.
+        ///
+        /// Returns `InsufficientBalance` error if there are not enough tokens on
+        /// the account balance of `from`.
+        #[ink(message)]
+        fn transfer_from(
+            &mut self,
+            from: AccountId,
+            to: AccountId,
+            value: Balance,
+            _data: Vec<u8>,
+        ) -> core::result::Result<(), PSP22Error> {
+            let caller = self.env().caller();
+            let allowance = self.allowance_impl(&from, &caller);
+            if allowance < value {
+                return Err(PSP22Error::InsufficientAllowance);
+            }
+            self.transfer_impl(&from, &to, value)?;
+            self.approve_impl(&from, &caller, allowance - value);
+            Ok(())
+        }
+
+        /// Allows `spender` to withdraw from the caller's account multiple times, up to
+        /// the `value` amount.
+        ///
+        /// If this function is called again it overwrites the current allowance with
+        /// `value`.
+        ///
+        /// An `Approval` event is emitted.
+        #[ink(message)]
+        fn approve(
+            &mut self,
+            spender: AccountId,
+            value: Balance,
+        ) -> core::result::Result<(), PSP22Error> {
+            let owner = self.env().caller();
+            self.approve_impl(&owner, &spender, value);
+            Ok(())
+        }
+
+        /// Increases the amount which `spender` is allowed to withdraw from the caller's
+        /// account by `delta_value`.
+        ///
+        /// An `Approval` event is emitted.
+        #[ink(message)]
+        fn increase_allowance(
+            &mut self,
+            spender: AccountId,
+            delta_value: Balance,
+        ) -> core::result::Result<(), PSP22Error> {
+            let owner = self.env().caller();
+            let allowance = self.allowance_impl(&owner, &spender);
+            self.approve_impl(&owner, &spender, allowance.saturating_add(delta_value));
+            Ok(())
+        }
+
+        /// Decreases the amount which `spender` is allowed to withdraw from the caller's
+        /// account by `delta_value`.
+        ///
+        /// An `Approval` event is emitted.
+        ///
+        /// # Errors
+        ///
+        /// Returns `InsufficientAllowance` error if the allowance is less than `delta_value`.
+        #[ink(message)]
+        fn decrease_allowance(
+            &mut self,
+            spender: AccountId,
+            delta_value: Balance,
+        ) -> core::result::Result<(), PSP22Error> {
+            let owner = self.env().caller();
+            let allowance = self.allowance_impl(&owner, &spender);
+            if allowance < delta_value {
+                return Err(PSP22Error::InsufficientAllowance);
+            }
+            self.approve_impl(&owner, &spender, allowance - delta_value);
+            Ok(())
+        }
+    }
+
+    impl PSP22Metadata for ZeitFund {
+        /// Returns the name of the share token, if set.
+        #[ink(message)]
+        fn token_name(&self) -> Option<String> {
+            self.metadata.name.clone()
+        }
+
+        /// Returns the symbol of the share token, if set.
+        #[ink(message)]
+        fn token_symbol(&self) -> Option<String> {
+            self.metadata.symbol.clone()
+        }
+
+        /// Returns the decimals of the share token, which are the same as ZTG's.
+        #[ink(message)]
+        fn token_decimals(&self) -> u8 {
+            SHARE_DECIMALS
+        }
+    }
+
+    /// Unit tests in Rust are normally defined within such a `#[cfg(test)]`
+    /// module and test functions are marked with a `#[test]` attribute.
+    /// The below code is technically just normal Rust code.
+    #[cfg(test)]
+    mod tests {
+        // Imports all the definitions from the outer scope so we can use them here.
+        // use super::*;
+
+        // TODO: write tests if you have time
+
+        use super::ZeitFund;
+        use crate::zeit_fund::{
+            Action, ActionStatus, CommitteeAction, CommitteeProposal, Environment, Error,
+            FeeConfig, FundState, LegacyPosition, ManagerCall, ManagerVesting, OpenEndedConfig,
+            Position, ProposalAction, QueuedAction, RiskLimits, Role, TimelockConfig,
+            TokenMetadata, DIVIDEND_PRECISION, LIQUIDATION_GRACE_PERIOD, MANAGER_VOTE_TIMELOCK,
+            NAV_PRECISION, STORAGE_VERSION, YEAR,
+        };
+        use crate::{contract_address, predict_dividend_wallet_address};
+        use crate::{
+            AssetManagerCall, LegacyZeitgeistAsset, PSP22Error, PSP22Metadata,
+            PredictionMarketsCall, RuntimeCall, SwapsCall, ZeitgeistAsset, PSP22,
+        };
+        use dividend_wallet::EnvFailure;
+        use ink::primitives::{AccountId, Hash};
+        use ink::storage::traits::StorageKey;
+
+        /// The funding deadline used by funds created in tests.
+        const FUNDING_DEADLINE: u64 = 1_000_000;
+
+        /// The maturity used by funds created in tests.
+        const MATURITY: u64 = 2_000_000;
+
+        /// The guardian of funds created in tests.
+        fn test_guardian() -> AccountId {
+            AccountId::from([0x0A; 32])
+        }
+
+        /// The vesting of the manager's shares in locked funds created in tests.
+        fn test_vesting() -> ManagerVesting {
+            ManagerVesting {
+                cliff: YEAR,
+                duration: 4 * YEAR,
+            }
+        }
+
+        /// Creates a fund without a dividend wallet (for testing purposes).
+        fn create_fund_no_wallet(manager: AccountId, total_shares: u128, locked: bool) -> ZeitFund {
+            ZeitFund::no_instantiation(
+                manager,
+                test_guardian(),
+                TokenMetadata::default(),
+                total_shares,
+                locked.then_some(test_vesting()),
+                FUNDING_DEADLINE,
+                MATURITY,
+                FeeConfig::default(),
+                open_mandate(),
+                TimelockConfig::default(),
+                None,
+                manager,
+            )
+        }
+
+        /// Risk limits that allow markets and pools 0-9 with full exposure.
+        fn open_mandate() -> RiskLimits {
+            RiskLimits {
+                allowed_markets: (0..10).collect(),
+                allowed_pools: (0..10).collect(),
+                max_market_exposure_bps: 10_000,
+                max_total_exposure_bps: 10_000,
+            }
+        }
+
+        /// Sends a lot of ZTG/DEV to a wallet.
+        fn megafund_wallet(wallet: AccountId) {
+            ink::env::test::set_account_balance::<ink::env::DefaultEnvironment>(
+                wallet,
+                100_000_000_000_000_000,
+            );
+        }
+
+        #[ink::test]
+        fn funding_works() {
+            let caller = AccountId::from([0x01; 32]);
+            let total_shares = 1_000_000_000_000;
+            let mut contract = create_fund_no_wallet(caller, total_shares, true);
+
+            assert_eq!(contract.balance_of(AccountId::from([0; 32])), total_shares);
+
+            let half_transfer = 500_000_000_000;
+            megafund_wallet(caller);
+
+            // Assert transfers
+            ink::env::pay_with_call!(contract.fund(), half_transfer).unwrap();
+            let balance = contract.balance_of(caller);
+            assert_eq!(balance, half_transfer);
+
+            // Assert failure to transfer over
+            let res = ink::env::pay_with_call!(contract.fund(), half_transfer + 1);
+            assert_eq!(res, Err(Error::FundingTooMuch));
+
+            ink::env::pay_with_call!(contract.fund(), half_transfer).unwrap();
+            let balance = contract.balance_of(caller);
+            assert_eq!(balance, total_shares);
+
+            // Assert that goal is reached
+            assert!(contract.is_funded());
+
+            // Assert failure to fund an active fund
+            ink::env::test::set_account_balance::<ink::env::DefaultEnvironment>(caller, 1);
+            let res = ink::env::pay_with_call!(contract.fund(), 1);
+            assert_eq!(res, Err(Error::NotAllowedInState(FundState::Active)));
+        }
+
+        #[ink::test]
+        fn manager_token_lock_works() {
+            let manager = AccountId::from([0x01; 32]);
+            let total_shares = 1_000_000_000_000;
+            let mut contract = create_fund_no_wallet(manager, total_shares, true);
+
+            assert_eq!(contract.balance_of(AccountId::from([0; 32])), total_shares);
+            assert!(contract.manager_is_locked());
+
+            // Manager will fund with 50
+            let half_transfer = 500_000_000_000;
+            ink::env::test::set_account_balance::<ink::env::DefaultEnvironment>(
+                manager,
+                half_transfer,
+            );
+            ink::env::pay_with_call!(contract.fund(), half_transfer).unwrap();
+            assert_eq!(contract.balance_of(manager), half_transfer);
+            assert_eq!(contract.manager_shares(), half_transfer);
+
+            // Assert that the manager can't transfer
+            let res = contract.transfer(AccountId::from([0x08; 32]), half_transfer, vec![]);
+            assert_eq!(res, Err(Error::ManagerSharesAreLocked.into()));
+        }
+
+        #[ink::test]
+        fn manager_shares_vest_after_the_cliff() {
+            let manager = AccountId::from([0x01; 32]);
+            let user = AccountId::from([0x04; 32]);
+            let mut fund = create_funded_fund(manager, user);
+            assert_eq!(fund.manager_vesting(), Some(test_vesting()));
+
+            ink::env::test::set_caller::<Environment>(manager);
+            ink::env::test::set_block_timestamp::<Environment>(YEAR - 1);
+            assert_eq!(fund.manager_unlocked_shares(), 0);
+            assert_eq!(
+                fund.transfer(user, 1, vec![]),
+                Err(Error::ManagerSharesAreLocked.into())
+            );
+
+            // A quarter unlocks at the cliff, then the rest unlocks linearly
+            ink::env::test::set_block_timestamp::<Environment>(YEAR);
+            assert_eq!(fund.manager_locked_shares(), 188);
+            assert_eq!(fund.manager_unlocked_shares(), 62);
+            ink::env::test::set_block_timestamp::<Environment>(2 * YEAR);
+            assert_eq!(fund.manager_unlocked_shares(), 125);
+            assert_eq!(
+                fund.transfer(user, 126, vec![]),
+                Err(Error::ManagerSharesAreLocked.into())
+            );
+            assert_eq!(fund.transfer(user, 25, vec![]), Ok(()));
+            assert_eq!(fund.manager_locked_shares(), 125);
+            assert_eq!(fund.manager_unlocked_shares(), 100);
+
+            // Shares the manager receives after activation are not locked
+            ink::env::test::set_caller::<Environment>(user);
+            fund.transfer(manager, 50, vec![]).unwrap();
+            assert_eq!(fund.manager_unlocked_shares(), 150);
+
+            ink::env::test::set_block_timestamp::<Environment>(4 * YEAR);
+            assert!(!fund.manager_is_locked());
+            assert_eq!(fund.manager_unlocked_shares(), 275);
+        }
+
+        #[ink::test]
+        fn liquidation_unlocks_the_manager_shares() {
+            let manager = AccountId::from([0x01; 32]);
+            let user = AccountId::from([0x04; 32]);
+            let mut fund = create_funded_fund(manager, user);
+            assert!(fund.manager_is_locked());
+            assert_eq!(fund.manager_locked_shares(), 250);
+
+            ink::env::test::set_caller::<Environment>(manager);
+            ink::env::test::set_block_timestamp::<Environment>(MATURITY);
+            fund.liquidate().unwrap();
+            assert!(!fund.manager_is_locked());
+            assert_eq!(fund.manager_unlocked_shares(), 250);
+            assert_eq!(fund.transfer(user, 250, vec![]), Ok(()));
+        }
+
+        #[ink::test]
+        fn replaced_manager_keeps_vesting() {
+            let manager = AccountId::from([0x01; 32]);
+            let user = AccountId::from([0x04; 32]);
+            let new_manager = AccountId::from([0x05; 32]);
+            let mut fund = create_funded_fund(manager, user);
+
+            // Shareholders replace the manager in the middle of the vesting
+            ink::env::test::set_block_timestamp::<Environment>(2 * YEAR);
+            ink::env::test::set_caller::<Environment>(user);
+            let id = fund.propose_manager_replacement(new_manager).unwrap();
+            fund.vote_manager_replacement(id).unwrap();
+            let now = 2 * YEAR + MANAGER_VOTE_TIMELOCK;
+            ink::env::test::set_block_timestamp::<Environment>(now);
+            fund.execute_manager_replacement(id).unwrap();
+            fund.transfer(new_manager, 100, vec![]).unwrap();
+
+            // The new manager's shares are not locked
+            assert_eq!(fund.manager_vesting_account(), manager);
+            assert!(!fund.manager_is_locked());
+            assert_eq!(fund.manager_locked_shares(), 0);
+            assert_eq!(fund.manager_unlocked_shares(), 100);
+            ink::env::test::set_caller::<Environment>(new_manager);
+            assert_eq!(fund.transfer(user, 100, vec![]), Ok(()));
+
+            // The replaced manager's shares keep vesting on schedule
+            let locked = test_vesting().locked(250, now);
+            assert!(locked > 0);
+            ink::env::test::set_caller::<Environment>(manager);
+            assert_eq!(
+                fund.transfer(user, 250 - locked + 1, vec![]),
+                Err(Error::ManagerSharesAreLocked.into())
+            );
+            assert_eq!(fund.transfer(user, 250 - locked, vec![]), Ok(()));
+            assert_eq!(fund.redeem_in_kind(1), Err(Error::ManagerSharesAreLocked));
+
+            ink::env::test::set_block_timestamp::<Environment>(4 * YEAR);
+            assert_eq!(fund.transfer(user, locked, vec![]), Ok(()));
+        }
+
+        #[ink::test]
+        fn token_based_dividend_calculation_works() {
+            let manager = AccountId::from([0x01; 32]);
+            let user = AccountId::from([0x04; 32]);
+            let total_shares = 100_000_000_000;
+            ink::env::test::set_callee::<Environment>(AccountId::from([0xAA; 32]));
+            let mut fund = create_fund_no_wallet(manager, total_shares, false);
+
+            // Manager will fund with 1/4 of the shares
+            let quarter_transfer = total_shares / 4;
+            megafund_wallet(manager);
+            ink::env::pay_with_call!(fund.fund(), quarter_transfer).unwrap();
+            assert_eq!(fund.balance_of(manager), quarter_transfer);
+            assert_eq!(fund.manager_shares(), quarter_transfer);
+
+            // Another account will fund with 3/4 of the shares
+            ink::env::test::set_caller::<Environment>(user);
+            megafund_wallet(user);
+            ink::env::pay_with_call!(fund.fund(), quarter_transfer * 3).unwrap();
+            assert_eq!(fund.balance_of(user), quarter_transfer * 3);
+            assert!(fund.is_funded());
+
+            // NOTE:    Cannot do fund.issue_dividend() since it calls runtime. Instead,
+            //          we manually record the dividend.
+
+            // "Issue" dividend by cheating
+            let dividend_amount = total_shares / 2;
+            fund.record_dividend(ZeitgeistAsset::Ztg, dividend_amount);
+
+            // Claim values should be proportional to the tokens
+            let manager_dividend = fund.calc_dividend(manager);
+            assert_eq!(manager_dividend, dividend_amount / 4);
+            let user_dividend = fund.calc_dividend(user);
+            assert_eq!(user_dividend, dividend_amount / 4 * 3);
+
+            // "Issue" second dividend by cheating
+            let second_dividend_amount = total_shares / 4;
+            fund.record_dividend(ZeitgeistAsset::Ztg, second_dividend_amount);
+
+            // Claim values should sum up
+            let manager_dividend = fund.calc_dividend(manager);
+            assert_eq!(
+                manager_dividend,
+                (dividend_amount + second_dividend_amount) / 4
+            );
+            let user_dividend = fund.calc_dividend(user);
+            assert_eq!(
+                user_dividend,
+                (dividend_amount + second_dividend_amount) / 4 * 3
+            );
+        }
+
+        /// The dividend calculation before the accumulator, which scanned every dividend.
+        fn legacy_calc_dividend(
+            dividends: &[(u64, u128)],
+            last_claimed: u64,
+            user_balance: u128,
+            total_supply: u128,
+        ) -> u128 {
+            let sum: u128 = dividends
+                .iter()
+                .filter(|(timestamp, _)| *timestamp > last_claimed)
+                .map(|(_, amount)| amount)
+                .sum();
+            let buffer = 1_000_000_000_000;
+            let percentage = (user_balance * buffer) / total_supply;
+            (sum * percentage) / buffer
+        }
+
+        #[ink::test]
+        fn dividend_accumulator_matches_legacy_calculation() {
+            let manager = AccountId::from([0x01; 32]);
+            let users = [
+                manager,
+                AccountId::from([0x04; 32]),
+                AccountId::from([0x05; 32]),
+            ];
+            let shares = [100_000_000_000, 250_000_000_000, 650_000_000_000];
+            let total_shares = shares.iter().sum();
+            ink::env::test::set_callee::<Environment>(AccountId::from([0xAA; 32]));
+            let mut fund = create_fund_no_wallet(manager, total_shares, false);
+            for (user, amount) in users.iter().zip(shares) {
+                ink::env::test::set_caller::<Environment>(*user);
+                megafund_wallet(*user);
+                ink::env::pay_with_call!(fund.fund(), amount).unwrap();
+            }
+            assert!(fund.is_funded());
+
+            let mut dividends = Vec::new();
+            for (i, amount) in [10_000_000_000, 1_234_567_891, 77_000_000_000_000]
+                .into_iter()
+                .enumerate()
+            {
+                let timestamp = (i as u64 + 1) * 100;
+                ink::env::test::set_block_timestamp::<Environment>(timestamp);
+                fund.record_dividend(ZeitgeistAsset::Ztg, amount);
+                dividends.push((timestamp, amount));
+
+                for (user, balance) in users.iter().zip(shares) {
+                    let legacy = legacy_calc_dividend(&dividends, 0, balance, total_shares);
+                    let accumulated = fund.calc_dividend(*user);
+                    // Both round down, but at different steps
+                    assert!(legacy.abs_diff(accumulated) <= dividends.len() as u128);
+                }
+            }
+
+            // Exact for evenly divisible amounts, using a new contract account
+            ink::env::test::set_callee::<Environment>(AccountId::from([0xBB; 32]));
+            let mut fund = create_fund_no_wallet(manager, total_shares, false);
+            for (user, amount) in users.iter().zip(shares) {
+                ink::env::test::set_caller::<Environment>(*user);
+                ink::env::pay_with_call!(fund.fund(), amount).unwrap();
+            }
+            fund.record_dividend(ZeitgeistAsset::Ztg, total_shares);
+            fund.record_dividend(ZeitgeistAsset::Ztg, total_shares * 3);
+            let dividends = [(1, total_shares), (2, total_shares * 3)];
+            for (user, balance) in users.iter().zip(shares) {
+                assert_eq!(
+                    fund.calc_dividend(*user),
+                    legacy_calc_dividend(&dividends, 0, balance, total_shares)
+                );
+            }
+        }
+
+        #[ink::test]
+        fn claiming_resets_dividend() {
+            let manager = AccountId::from([0x01; 32]);
+            let user = AccountId::from([0x04; 32]);
+            let mut fund = create_funded_fund(manager, user);
+
+            // Claiming with no dividend doesn't call the dividend wallet
+            ink::env::test::set_caller::<Environment>(user);
+            assert_eq!(fund.claim(), Ok(0));
+            fund.record_dividend(ZeitgeistAsset::Ztg, 1_000);
+            assert_eq!(fund.calc_dividend(user), 750);
+
+            // Only dividends issued after the last claim are owed
+            fund.reset_dividend(user, &ZeitgeistAsset::Ztg);
+            assert_eq!(fund.calc_dividend(user), 0);
+            fund.record_dividend(ZeitgeistAsset::Ztg, 2_000);
+            assert_eq!(fund.calc_dividend(user), 1_500);
+            assert_eq!(fund.calc_dividend(manager), 750);
+        }
+
+        #[ink::test]
+        fn dividends_follow_holdings_at_issuance_through_transfers() {
+            let manager = AccountId::from([0x01; 32]);
+            let user = AccountId::from([0x04; 32]);
+            let buyer = AccountId::from([0x05; 32]);
+            let mut fund = create_funded_fund(manager, user);
+
+            // Manager 250, user 750
+            fund.record_dividend(ZeitgeistAsset::Ztg, 1_000);
+
+            // User sells 500 shares, which doesn't move the first dividend
+            ink::env::test::set_caller::<Environment>(user);
+            fund.transfer(buyer, 500, vec![]).unwrap();
+            assert_eq!(fund.calc_dividend(user), 750);
+            assert_eq!(fund.calc_dividend(buyer), 0);
+
+            // Manager 250, user 250, buyer 500
+            fund.record_dividend(ZeitgeistAsset::Ztg, 2_000);
+            assert_eq!(fund.calc_dividend(manager), 250 + 500);
+            assert_eq!(fund.calc_dividend(user), 750 + 500);
+            assert_eq!(fund.calc_dividend(buyer), 1_000);
+
+            // Buyer sends everything back through an allowance
+            ink::env::test::set_caller::<Environment>(buyer);
+            fund.approve(user, 500).unwrap();
+            ink::env::test::set_caller::<Environment>(user);
+            fund.transfer_from(buyer, user, 500, vec![]).unwrap();
+
+            // Manager 250, user 750, buyer 0
+            fund.record_dividend(ZeitgeistAsset::Ztg, 4_000);
+            assert_eq!(fund.calc_dividend(manager), 250 + 500 + 1_000);
+            assert_eq!(fund.calc_dividend(user), 750 + 500 + 3_000);
+            assert_eq!(fund.calc_dividend(buyer), 1_000);
+        }
+
+        #[ink::test]
+        fn funding_does_not_earn_earlier_dividends() {
+            let manager = AccountId::from([0x01; 32]);
+            let user = AccountId::from([0x04; 32]);
+            ink::env::test::set_callee::<Environment>(AccountId::from([0xAA; 32]));
+            let mut fund = create_fund_no_wallet(manager, 1_000, false);
+            megafund_wallet(manager);
+            megafund_wallet(user);
+            ink::env::pay_with_call!(fund.fund(), 500).unwrap();
+
+            // A dividend while half of the shares are unsold is split between the sold ones
+            fund.record_dividend(ZeitgeistAsset::Ztg, 1_000);
+            assert_eq!(fund.calc_dividend(manager), 1_000);
+
+            // Shares minted afterwards don't earn it
+            ink::env::test::set_caller::<Environment>(user);
+            ink::env::pay_with_call!(fund.fund(), 500).unwrap();
+            assert_eq!(fund.calc_dividend(user), 0);
+
+            fund.record_dividend(ZeitgeistAsset::Ztg, 1_000);
+            assert_eq!(fund.calc_dividend(manager), 1_500);
+            assert_eq!(fund.calc_dividend(user), 500);
+        }
+
+        #[ink::test]
+        fn dividends_are_tracked_per_asset() {
+            let manager = AccountId::from([0x01; 32]);
+            let user = AccountId::from([0x04; 32]);
+            let buyer = AccountId::from([0x05; 32]);
+            let foreign = ZeitgeistAsset::ForeignAsset(1);
+            let outcome = ZeitgeistAsset::CategoricalOutcome(7, 1);
+            let mut fund = create_funded_fund(manager, user);
+
+            fund.record_dividend(ZeitgeistAsset::Ztg, 1_000);
+            fund.record_dividend(foreign.clone(), 2_000);
+            fund.record_dividend(foreign.clone(), 2_000);
+            assert_eq!(
+                fund.dividend_assets(),
+                [ZeitgeistAsset::Ztg, foreign.clone()]
+            );
+            assert_eq!(fund.calc_dividend(user), 750);
+            assert_eq!(fund.calc_asset_dividend(user, foreign.clone()), 3_000);
+            assert_eq!(fund.calc_asset_dividend(user, outcome.clone()), 0);
+
+            // Transfers settle every asset
+            ink::env::test::set_caller::<Environment>(user);
+            fund.transfer(buyer, 750, vec![]).unwrap();
+            fund.record_dividend(outcome.clone(), 100);
+            assert_eq!(fund.calc_asset_dividend(user, foreign.clone()), 3_000);
+            assert_eq!(fund.calc_asset_dividend(user, outcome.clone()), 0);
+            assert_eq!(fund.calc_asset_dividend(buyer, foreign), 0);
+            assert_eq!(fund.calc_asset_dividend(buyer, outcome.clone()), 75);
+            assert_eq!(fund.calc_asset_dividend(manager, outcome), 25);
+
+            // Claiming one asset leaves the others untouched
+            fund.reset_dividend(user, &ZeitgeistAsset::Ztg);
+            assert_eq!(fund.calc_dividend(user), 0);
+            assert_eq!(
+                fund.calc_asset_dividend(user, ZeitgeistAsset::ForeignAsset(1)),
+                3_000
+            );
+        }
+
+        #[ink::test]
+        fn funding_after_deadline_fails() {
+            let caller = AccountId::from([0x01; 32]);
+            let mut contract = create_fund_no_wallet(caller, 1_000, false);
+            megafund_wallet(caller);
+
+            ink::env::test::set_block_timestamp::<Environment>(FUNDING_DEADLINE + 1);
+            let res = ink::env::pay_with_call!(contract.fund(), 500);
+            assert_eq!(res, Err(Error::NotAllowedInState(FundState::Failed)));
+            assert_eq!(contract.balance_of(caller), 0);
+        }
+
+        #[ink::test]
+        fn refund_works_after_failed_raise() {
+            let manager = AccountId::from([0x01; 32]);
+            let user = AccountId::from([0x04; 32]);
+            let contract_id = AccountId::from([0xAA; 32]);
+            ink::env::test::set_callee::<Environment>(contract_id);
+            let mut fund = create_fund_no_wallet(manager, 1_000, true);
+
+            // Manager and user fund less than the goal
+            megafund_wallet(manager);
+            ink::env::pay_with_call!(fund.fund(), 200).unwrap();
+            ink::env::test::set_caller::<Environment>(user);
+            ink::env::test::set_account_balance::<Environment>(user, 300);
+            ink::env::pay_with_call!(fund.fund(), 300).unwrap();
+            assert!(!fund.is_funded());
+
+            // No refunds before the deadline
+            assert_eq!(
+                fund.refund(),
+                Err(Error::NotAllowedInState(FundState::Fundraising))
+            );
+
+            ink::env::test::set_block_timestamp::<Environment>(FUNDING_DEADLINE + 1);
+            assert_eq!(fund.refund(), Ok(300));
+            assert_eq!(fund.balance_of(user), 0);
+            assert_eq!(fund.initial_funding_amount(), 200);
+            assert_eq!(
+                ink::env::test::get_account_balance::<Environment>(user),
+                Ok(300)
+            );
+            assert_eq!(fund.refund(), Err(Error::NothingToRefund));
+
+            // Locked manager shares can be refunded as well
+            ink::env::test::set_caller::<Environment>(manager);
+            assert_eq!(fund.refund(), Ok(200));
+            assert_eq!(fund.balance_of(AccountId::from([0; 32])), 1_000);
+            assert_eq!(
+                ink::env::test::get_account_balance::<Environment>(contract_id),
+                Ok(0)
+            );
+        }
+
+        #[ink::test]
+        fn refund_fails_after_successful_raise() {
+            let caller = AccountId::from([0x01; 32]);
+            let mut contract = create_fund_no_wallet(caller, 1_000, false);
+            megafund_wallet(caller);
+            ink::env::pay_with_call!(contract.fund(), 1_000).unwrap();
+            assert!(contract.is_funded());
+
+            ink::env::test::set_block_timestamp::<Environment>(FUNDING_DEADLINE + 1);
+            assert_eq!(
+                contract.refund(),
+                Err(Error::NotAllowedInState(FundState::Active))
+            );
+            assert_eq!(contract.balance_of(caller), 1_000);
+        }
+
+        /// Creates a fund that is completely funded by the manager (1/4) and a user (3/4).
+        fn create_funded_fund(manager: AccountId, user: AccountId) -> ZeitFund {
+            ink::env::test::set_callee::<Environment>(AccountId::from([0xAA; 32]));
+            let mut fund = create_fund_no_wallet(manager, 1_000, true);
+            ink::env::test::set_caller::<Environment>(manager);
+            megafund_wallet(manager);
+            ink::env::pay_with_call!(fund.fund(), 250).unwrap();
+            ink::env::test::set_caller::<Environment>(user);
+            megafund_wallet(user);
+            ink::env::pay_with_call!(fund.fund(), 750).unwrap();
+            assert!(fund.is_funded());
+            fund
+        }
+
+        #[ink::test]
+        fn liquidation_respects_maturity_and_grace_period() {
+            let manager = AccountId::from([0x01; 32]);
+            let user = AccountId::from([0x04; 32]);
+            let mut fund = create_funded_fund(manager, user);
+
+            // Nobody can liquidate before maturity
+            ink::env::test::set_caller::<Environment>(manager);
+            assert_eq!(fund.liquidate(), Err(Error::MaturityNotReached));
+
+            // Only the manager can liquidate during the grace period
+            ink::env::test::set_block_timestamp::<Environment>(MATURITY);
+            ink::env::test::set_caller::<Environment>(user);
+            assert_eq!(fund.liquidate(), Err(Error::GracePeriodNotOver));
+
+            // Anyone can liquidate after the grace period
+            ink::env::test::set_block_timestamp::<Environment>(MATURITY + LIQUIDATION_GRACE_PERIOD);
+            assert_eq!(fund.liquidate(), Ok(()));
+            assert_eq!(fund.state(), FundState::Liquidating);
+            assert_eq!(
+                fund.liquidate(),
+                Err(Error::NotAllowedInState(FundState::Liquidating))
+            );
+        }
+
+        #[ink::test]
+        fn liquidating_only_allows_exits() {
+            let manager = AccountId::from([0x01; 32]);
+            let user = AccountId::from([0x04; 32]);
+            let mut fund = create_funded_fund(manager, user);
+
+            ink::env::test::set_caller::<Environment>(manager);
+            ink::env::test::set_block_timestamp::<Environment>(MATURITY);
+            fund.liquidate().unwrap();
+
+            let res = fund.prediction_market_call(PredictionMarketsCall::BuyCompleteSet {
+                market_id: 1,
+                amount: 100,
+            });
+            assert_eq!(res, Err(Error::OnlyExitsWhileLiquidating));
+            let res = fund.swap_call(SwapsCall::SwapExactAmountIn {
+                pool_id: 1,
+                asset_in: ZeitgeistAsset::Ztg,
+                asset_amount_in: 100,
+                asset_out: ZeitgeistAsset::CategoricalOutcome(1, 0),
+                min_asset_amount_out: None,
+                max_price: None,
+            });
+            assert_eq!(res, Err(Error::OnlyExitsWhileLiquidating));
+            assert_eq!(
+                fund.issue_dividend(ZeitgeistAsset::Ztg, 100),
+                Err(Error::NotAllowedInState(FundState::Liquidating))
+            );
+
+            assert!(PredictionMarketsCall::RedeemShares { market_id: 1 }.is_exit());
+            assert!(SwapsCall::SwapExactAmountIn {
+                pool_id: 1,
+                asset_in: ZeitgeistAsset::CategoricalOutcome(1, 0),
+                asset_amount_in: 100,
+                asset_out: ZeitgeistAsset::Ztg,
+                min_asset_amount_out: None,
+                max_price: None,
+            }
+            .is_exit());
+        }
+
+        #[ink::test]
+        fn redeem_pays_out_pro_rata() {
+            let manager = AccountId::from([0x01; 32]);
+            let user = AccountId::from([0x04; 32]);
+            let contract_id = AccountId::from([0xAA; 32]);
+            let mut fund = create_funded_fund(manager, user);
+
+            // Cannot redeem before liquidation
+            ink::env::test::set_caller::<Environment>(user);
+            assert_eq!(
+                fund.redeem(750),
+                Err(Error::NotAllowedInState(FundState::Active))
+            );
+
+            ink::env::test::set_caller::<Environment>(manager);
+            ink::env::test::set_block_timestamp::<Environment>(MATURITY);
+            fund.liquidate().unwrap();
+
+            // The fund made a profit, doubling its ZTG
+            ink::env::test::set_account_balance::<Environment>(contract_id, 2_000);
+
+            // Locked manager shares can be redeemed once liquidating
+            assert_eq!(fund.redeem(250), Ok(500));
+            assert_eq!(fund.balance_of(manager), 0);
+
+            ink::env::test::set_caller::<Environment>(user);
+            let user_balance = ink::env::test::get_account_balance::<Environment>(user).unwrap();
+            assert_eq!(fund.redeem(800), Err(Error::InsufficientBalance));
+            assert_eq!(fund.redeem(0), Err(Error::ZeroShares));
+            assert_eq!(fund.redeem(375), Ok(750));
+            assert_eq!(fund.redeem(375), Ok(750));
+            assert_eq!(
+                ink::env::test::get_account_balance::<Environment>(user),
+                Ok(user_balance + 1_500)
+            );
+            assert_eq!(
+                ink::env::test::get_account_balance::<Environment>(contract_id),
+                Ok(0)
+            );
+            assert_eq!(fund.balance_of(AccountId::from([0; 32])), 1_000);
+            assert_eq!(fund.state(), FundState::Closed);
+        }
+
+        #[ink::test]
+        fn state_transitions_work() {
+            let manager = AccountId::from([0x01; 32]);
+            let user = AccountId::from([0x04; 32]);
+            ink::env::test::set_callee::<Environment>(AccountId::from([0xAA; 32]));
+            let mut fund = create_fund_no_wallet(manager, 1_000, false);
+            assert_eq!(fund.state(), FundState::Fundraising);
+            assert!(fund.is_allowed(Action::Fund));
+            assert!(!fund.is_allowed(Action::SwapCall));
+
+            megafund_wallet(manager);
+            ink::env::pay_with_call!(fund.fund(), 1_000).unwrap();
+            assert_eq!(fund.state(), FundState::Active);
+
+            // Only the guardian can pause, and pausing blocks transfers
+            ink::env::test::set_caller::<Environment>(manager);
+            assert_eq!(fund.pause(), Err(Error::OnlyGuardianAllowed));
+            ink::env::test::set_caller::<Environment>(test_guardian());
+            fund.pause().unwrap();
+            assert_eq!(fund.state(), FundState::Paused);
+            ink::env::test::set_caller::<Environment>(manager);
+            assert_eq!(
+                fund.transfer(user, 10, vec![]),
+                Err(Error::NotAllowedInState(FundState::Paused).into())
+            );
+            assert_eq!(
+                fund.issue_dividend(ZeitgeistAsset::Ztg, 10),
+                Err(Error::NotAllowedInState(FundState::Paused))
+            );
+            assert_eq!(fund.claim(), Ok(0));
+            assert_eq!(fund.unpause(), Err(Error::OnlyGuardianAllowed));
+            ink::env::test::set_caller::<Environment>(test_guardian());
+            fund.unpause().unwrap();
+            assert_eq!(fund.state(), FundState::Active);
+            ink::env::test::set_caller::<Environment>(manager);
+            assert_eq!(fund.transfer(user, 10, vec![]), Ok(()));
+        }
+
+        #[ink::test]
+        fn paused_raise_can_still_be_refunded() {
+            let manager = AccountId::from([0x01; 32]);
+            let mut fund = create_fund_no_wallet(manager, 1_000, false);
+            megafund_wallet(manager);
+            ink::env::pay_with_call!(fund.fund(), 500).unwrap();
+
+            ink::env::test::set_caller::<Environment>(test_guardian());
+            fund.pause().unwrap();
+            ink::env::test::set_caller::<Environment>(manager);
+            assert_eq!(
+                ink::env::pay_with_call!(fund.fund(), 500),
+                Err(Error::NotAllowedInState(FundState::Paused))
+            );
+
+            // Unpausing returns to the raise
+            ink::env::test::set_caller::<Environment>(test_guardian());
+            fund.unpause().unwrap();
+            assert_eq!(fund.state(), FundState::Fundraising);
+            fund.pause().unwrap();
+
+            // The raise still fails at the deadline, so shares can be refunded
+            ink::env::test::set_block_timestamp::<Environment>(FUNDING_DEADLINE + 1);
+            assert_eq!(fund.state(), FundState::Failed);
+            ink::env::test::set_caller::<Environment>(manager);
+            assert_eq!(fund.refund(), Ok(500));
+        }
+
+        #[ink::test]
+        fn guardian_can_be_handed_over() {
+            let manager = AccountId::from([0x01; 32]);
+            let new_guardian = AccountId::from([0x0B; 32]);
+            let mut fund = create_fund_no_wallet(manager, 1_000, false);
+            assert_eq!(fund.guardian(), test_guardian());
+
+            ink::env::test::set_caller::<Environment>(manager);
+            assert_eq!(fund.set_guardian(manager), Err(Error::OnlyGuardianAllowed));
+            ink::env::test::set_caller::<Environment>(test_guardian());
+            fund.set_guardian(new_guardian).unwrap();
+            assert_eq!(fund.pause(), Err(Error::OnlyGuardianAllowed));
+            ink::env::test::set_caller::<Environment>(new_guardian);
+            assert_eq!(fund.pause(), Ok(()));
+        }
+
+        #[ink::test]
+        fn unfunded_fund_fails_after_deadline() {
+            let manager = AccountId::from([0x01; 32]);
+            let mut fund = create_fund_no_wallet(manager, 1_000, false);
+            megafund_wallet(manager);
+            ink::env::pay_with_call!(fund.fund(), 500).unwrap();
+            assert_eq!(fund.state(), FundState::Fundraising);
+
+            ink::env::test::set_block_timestamp::<Environment>(FUNDING_DEADLINE + 1);
+            assert_eq!(fund.state(), FundState::Failed);
+            assert!(fund.is_allowed(Action::Refund));
+            assert_eq!(
+                fund.transfer(AccountId::from([0x04; 32]), 10, vec![]),
+                Err(Error::NotAllowedInState(FundState::Failed).into())
+            );
+        }
+
+        #[ink::test]
+        fn allowed_states_table_is_explicit() {
+            use Action::*;
+            let actions = [
+                Fund,
+                Refund,
+                SwapCall,
+                PredictionMarketCall,
+                IssueDividend,
+                Claim,
+                Transfer,
+                Pause,
+                Unpause,
+                Liquidate,
+                Redeem,
+                Govern,
+                Fees,
+            ];
+            let allowed = |state: FundState| -> Vec<Action> {
+                actions.into_iter().filter(|a| state.allows(*a)).collect()
+            };
+
+            assert_eq!(
+                allowed(FundState::Fundraising),
+                [Fund, Claim, Transfer, Pause]
+            );
+            assert_eq!(
+                allowed(FundState::Active),
+                [
+                    SwapCall,
+                    PredictionMarketCall,
+                    IssueDividend,
+                    Claim,
+                    Transfer,
+                    Pause,
+                    Liquidate,
+                    Govern,
+                    Fees
+                ]
+            );
+            assert_eq!(allowed(FundState::Paused), [Claim, Unpause, Govern, Fees]);
+            assert_eq!(
+                allowed(FundState::Liquidating),
+                [
+                    SwapCall,
+                    PredictionMarketCall,
+                    Claim,
+                    Transfer,
+                    Pause,
+                    Redeem,
+                    Govern
+                ]
+            );
+            assert_eq!(allowed(FundState::Closed), [Claim]);
+            assert_eq!(allowed(FundState::Failed), [Refund]);
+        }
+
+        /// Creates a fund that charges `fees`, completely funded by the manager at timestamp 0.
+        fn create_funded_fund_with_fees(manager: AccountId, fees: FeeConfig) -> ZeitFund {
+            let mut fund = create_fund_with_fees(manager, fees);
+            megafund_wallet(manager);
+            ink::env::pay_with_call!(fund.fund(), 1_000).unwrap();
+            fund
+        }
+
+        /// Creates a fund that charges `fees` and is still raising.
+        fn create_fund_with_fees(manager: AccountId, fees: FeeConfig) -> ZeitFund {
+            ink::env::test::set_callee::<Environment>(AccountId::from([0xAA; 32]));
+            ink::env::test::set_caller::<Environment>(manager);
+            ZeitFund::no_instantiation(
+                manager,
+                test_guardian(),
+                TokenMetadata::default(),
+                1_000,
+                None,
+                FUNDING_DEADLINE,
+                MATURITY,
+                fees,
+                open_mandate(),
+                TimelockConfig::default(),
+                None,
+                manager,
+            )
+        }
+
+        #[ink::test]
+        fn management_fee_accrues_over_time() {
+            let manager = AccountId::from([0x01; 32]);
+            let recipient = AccountId::from([0x09; 32]);
+            let mut fund = create_funded_fund_with_fees(
+                manager,
+                FeeConfig {
+                    recipient,
+                    management_fee_bps: 200,
+                    ..Default::default()
+                },
+            );
+
+            // 2% a year is 1% after half a year
+            ink::env::test::set_block_timestamp::<Environment>(YEAR / 2);
+            assert_eq!(fund.pending_management_fee(), 10);
+            assert_eq!(fund.accrue_fees(), Ok((10, 0)));
+            assert_eq!(fund.balance_of(recipient), 10);
+            assert_eq!(fund.total_supply(), 1_010);
+            assert_eq!(fund.pending_management_fee(), 0);
+            assert!(fund.is_funded());
+
+            // Fees are charged one last time on liquidation, then stop accruing
+            ink::env::test::set_block_timestamp::<Environment>(YEAR);
+            fund.liquidate().unwrap();
+            assert_eq!(fund.balance_of(recipient), 20);
+            ink::env::test::set_block_timestamp::<Environment>(2 * YEAR);
+            assert_eq!(fund.pending_management_fee(), 0);
+            assert_eq!(
+                fund.accrue_fees(),
+                Err(Error::NotAllowedInState(FundState::Liquidating))
+            );
+        }
+
+        #[ink::test]
+        fn paused_raise_accrues_no_fees() {
+            let manager = AccountId::from([0x01; 32]);
+            let user = AccountId::from([0x04; 32]);
+            let recipient = AccountId::from([0x09; 32]);
+            let mut fund = create_fund_with_fees(
+                manager,
+                FeeConfig {
+                    recipient,
+                    management_fee_bps: 200,
+                    ..Default::default()
+                },
+            );
+            megafund_wallet(manager);
+            ink::env::pay_with_call!(fund.fund(), 500).unwrap();
+            ink::env::test::set_caller::<Environment>(user);
+            megafund_wallet(user);
+            ink::env::pay_with_call!(fund.fund(), 300).unwrap();
+
+            ink::env::test::set_caller::<Environment>(test_guardian());
+            fund.pause().unwrap();
+            ink::env::test::set_block_timestamp::<Environment>(FUNDING_DEADLINE);
+            assert_eq!(fund.pending_management_fee(), 0);
+            assert_eq!(fund.pending_performance_fee(), 0);
+            assert_eq!(
+                fund.accrue_fees(),
+                Err(Error::NotAllowedInState(FundState::Paused))
+            );
+            assert_eq!(fund.balance_of(recipient), 0);
+
+            // Contributors get back exactly what they paid in
+            ink::env::test::set_block_timestamp::<Environment>(FUNDING_DEADLINE + 1);
+            ink::env::test::set_caller::<Environment>(user);
+            assert_eq!(fund.refund(), Ok(300));
+            ink::env::test::set_caller::<Environment>(manager);
+            assert_eq!(fund.refund(), Ok(500));
+            assert_eq!(
+                fund.total_supply(),
+                fund.balance_of(AccountId::from([0; 32]))
+            );
+        }
+
+        #[ink::test]
+        fn performance_fee_respects_high_water_mark() {
+            let manager = AccountId::from([0x01; 32]);
+            let recipient = AccountId::from([0x09; 32]);
+            let mut fund = create_funded_fund_with_fees(
+                manager,
+                FeeConfig {
+                    recipient,
+                    performance_fee_bps: 2_000,
+                    crystallisation_period: 100,
+                    ..Default::default()
+                },
+            );
+            assert_eq!(fund.high_water_mark(), NAV_PRECISION);
+
+            // Only the manager reports NAV
+            ink::env::test::set_caller::<Environment>(recipient);
+            assert_eq!(fund.report_nav(2_000), Err(Error::OnlyManagerAllowed));
+            ink::env::test::set_caller::<Environment>(manager);
+            fund.report_nav(2_000).unwrap();
+
+            // 20% of the 1000 ZTG gain is 200 ZTG, or 111 shares after dilution
+            assert_eq!(fund.pending_performance_fee(), 111);
+
+            // Nothing is minted before the crystallisation period passes
+            ink::env::test::set_block_timestamp::<Environment>(99);
+            assert_eq!(fund.accrue_fees(), Ok((0, 0)));
+            ink::env::test::set_block_timestamp::<Environment>(100);
+            assert_eq!(fund.accrue_fees(), Ok((0, 111)));
+            assert_eq!(fund.balance_of(recipient), 111);
+            assert_eq!(fund.high_water_mark(), 2_000 * NAV_PRECISION / 1_111);
+
+            // No fee is charged again until the high-water mark is beaten
+            fund.report_nav(1_500).unwrap();
+            ink::env::test::set_block_timestamp::<Environment>(200);
+            assert_eq!(fund.accrue_fees(), Ok((0, 0)));
+            assert_eq!(fund.high_water_mark(), 2_000 * NAV_PRECISION / 1_111);
+            fund.report_nav(2_000).unwrap();
+            assert_eq!(fund.pending_performance_fee(), 0);
+        }
+
+        #[ink::test]
+        fn positions_are_recorded() {
+            let manager = AccountId::from([0x01; 32]);
+            let mut fund = create_fund_no_wallet(manager, 1_000, false);
+            let outcome = ZeitgeistAsset::CategoricalOutcome(7, 1);
+
+            fund.record_positions(&RuntimeCall::PredictionMarkets(
+                PredictionMarketsCall::BuyCompleteSet {
+                    market_id: 3,
+                    amount: 100,
+                },
+            ));
+            fund.record_positions(&RuntimeCall::Swaps(SwapsCall::PoolJoin {
+                pool_id: 5,
+                pool_amount: 50,
+                max_assets_in: vec![],
+            }));
+            fund.record_positions(&RuntimeCall::Swaps(SwapsCall::SwapExactAmountIn {
+                pool_id: 5,
+                asset_in: ZeitgeistAsset::Ztg,
+                asset_amount_in: 10,
+                asset_out: outcome.clone(),
+                min_asset_amount_out: None,
+                max_price: None,
+            }));
+            // Swapping back into ZTG does not add a position
+            fund.record_positions(&RuntimeCall::Swaps(SwapsCall::SwapExactAmountOut {
+                pool_id: 5,
+                asset_in: outcome.clone(),
+                max_asset_amount_in: None,
+                asset_out: ZeitgeistAsset::Ztg,
+                asset_amount_out: 1,
+                max_price: None,
+            }));
+            // Buying into a held market does not add it twice
+            fund.record_positions(&RuntimeCall::PredictionMarkets(
+                PredictionMarketsCall::BuyCompleteSet {
+                    market_id: 3,
+                    amount: 100,
+                },
+            ));
+
+            assert_eq!(
+                fund.positions(),
+                [
+                    Position::Market(3),
+                    Position::Pool(5),
+                    Position::Market(7),
+                    Position::Asset(outcome)
+                ]
+            );
+            assert_eq!(fund.markets(), [3, 7]);
+            assert_eq!(fund.pool_shares(5), 50);
+        }
+
+        #[ink::test]
+        fn positions_are_closed() {
+            let manager = AccountId::from([0x01; 32]);
+            let mut fund = create_fund_no_wallet(manager, 1_000, false);
+
+            fund.record_positions(&RuntimeCall::Swaps(SwapsCall::PoolJoin {
+                pool_id: 5,
+                pool_amount: 50,
+                max_assets_in: vec![],
+            }));
+            fund.record_positions(&RuntimeCall::Swaps(SwapsCall::SwapExactAmountOut {
+                pool_id: 5,
+                asset_in: ZeitgeistAsset::Ztg,
+                max_asset_amount_in: None,
+                asset_out: ZeitgeistAsset::CategoricalOutcome(7, 0),
+                asset_amount_out: 10,
+                max_price: None,
+            }));
+
+            // A pool is only closed once every pool share has been exited
+            fund.record_positions(&RuntimeCall::Swaps(SwapsCall::PoolExit {
+                pool_id: 5,
+                pool_amount: 20,
+                min_assets_out: vec![],
+            }));
+            assert_eq!(fund.pool_shares(5), 30);
+            assert!(fund.positions().contains(&Position::Pool(5)));
+            fund.record_positions(&RuntimeCall::Swaps(SwapsCall::PoolExit {
+                pool_id: 5,
+                pool_amount: 30,
+                min_assets_out: vec![],
+            }));
+            assert_eq!(fund.pool_shares(5), 0);
+            assert!(!fund.positions().contains(&Position::Pool(5)));
+
+            // Outcome assets and markets are closed once nothing of them is left
+            let outcome = ZeitgeistAsset::CategoricalOutcome(9, 0);
+            fund.record_positions(&RuntimeCall::Swaps(SwapsCall::SwapExactAmountIn {
+                pool_id: 5,
+                asset_in: ZeitgeistAsset::Ztg,
+                asset_amount_in: 10,
+                asset_out: outcome.clone(),
+                min_asset_amount_out: Some(40),
+                max_price: None,
+            }));
+            fund.record_positions(&RuntimeCall::PredictionMarkets(
+                PredictionMarketsCall::BuyCompleteSet {
+                    market_id: 11,
+                    amount: 10,
+                },
+            ));
+            assert!(fund.markets().contains(&9) && fund.markets().contains(&11));
+            fund.record_positions(&RuntimeCall::Swaps(SwapsCall::SwapExactAmountIn {
+                pool_id: 5,
+                asset_in: outcome.clone(),
+                asset_amount_in: 25,
+                asset_out: ZeitgeistAsset::Ztg,
+                min_asset_amount_out: None,
+                max_price: None,
+            }));
+            assert!(fund.positions().contains(&Position::Asset(outcome.clone())));
+            fund.record_positions(&RuntimeCall::Swaps(SwapsCall::SwapExactAmountIn {
+                pool_id: 5,
+                asset_in: outcome.clone(),
+                asset_amount_in: 15,
+                asset_out: ZeitgeistAsset::Ztg,
+                min_asset_amount_out: None,
+                max_price: None,
+            }));
+            fund.record_positions(&RuntimeCall::PredictionMarkets(
+                PredictionMarketsCall::SellCompleteSet {
+                    market_id: 11,
+                    amount: 10,
+                },
+            ));
+            assert!(!fund.positions().contains(&Position::Asset(outcome)));
+            assert_eq!(fund.markets(), [7]);
+
+            // Redeeming closes the market and its outcome assets
+            fund.record_positions(&RuntimeCall::PredictionMarkets(
+                PredictionMarketsCall::RedeemShares { market_id: 7 },
+            ));
+            assert_eq!(fund.positions(), []);
+            assert_eq!(fund.markets(), []);
+        }
+
+        #[ink::test]
+        fn holdings_are_recorded() {
+            let manager = AccountId::from([0x01; 32]);
+            let mut fund = create_fund_no_wallet(manager, 1_000, false);
+            let yes = ZeitgeistAsset::CategoricalOutcome(3, 0);
+            let no = ZeitgeistAsset::CategoricalOutcome(3, 1);
+
+            fund.record_positions(&RuntimeCall::PredictionMarkets(
+                PredictionMarketsCall::BuyCompleteSet {
+                    market_id: 3,
+                    amount: 100,
+                },
+            ));
+            // Swaps only record the least that the fund got
+            fund.record_positions(&RuntimeCall::Swaps(SwapsCall::SwapExactAmountIn {
+                pool_id: 5,
+                asset_in: ZeitgeistAsset::Ztg,
+                asset_amount_in: 10,
+                asset_out: yes.clone(),
+                min_asset_amount_out: Some(15),
+                max_price: None,
+            }));
+            assert_eq!(fund.complete_sets(3), 100);
+            assert_eq!(fund.asset_holdings(yes.clone()), 15);
+
+            // Selling more of an outcome than was swapped into breaks up complete sets
+            fund.record_positions(&RuntimeCall::Swaps(SwapsCall::SwapExactAmountIn {
+                pool_id: 5,
+                asset_in: no.clone(),
+                asset_amount_in: 40,
+                asset_out: ZeitgeistAsset::Ztg,
+                min_asset_amount_out: None,
+                max_price: None,
+            }));
+            assert_eq!(fund.complete_sets(3), 60);
+            assert_eq!(fund.asset_holdings(no), 0);
+
+            // Dividends of an asset leave the fund
+            fund.record_positions(&RuntimeCall::AssetManager(AssetManagerCall::Transfer {
+                dest: manager.into(),
+                currency_id: yes.clone(),
+                amount: 5,
+            }));
+            assert_eq!(fund.asset_holdings(yes.clone()), 10);
+
+            fund.record_positions(&RuntimeCall::PredictionMarkets(
+                PredictionMarketsCall::SellCompleteSet {
+                    market_id: 3,
+                    amount: 20,
+                },
+            ));
+            assert_eq!(fund.complete_sets(3), 40);
+            fund.record_positions(&RuntimeCall::PredictionMarkets(
+                PredictionMarketsCall::RedeemShares { market_id: 3 },
+            ));
+            assert_eq!(fund.complete_sets(3), 0);
+            assert_eq!(fund.asset_holdings(yes), 0);
+        }
+
+        #[ink::test]
+        fn redeemable_in_kind_is_pro_rata() {
+            let manager = AccountId::from([0x01; 32]);
+            let user = AccountId::from([0x04; 32]);
+            let contract_id = AccountId::from([0xAA; 32]);
+            let outcome = ZeitgeistAsset::CategoricalOutcome(2, 0);
+            let mut fund = create_funded_fund(manager, user);
+
+            fund.record_positions(&RuntimeCall::Swaps(SwapsCall::PoolJoin {
+                pool_id: 1,
+                pool_amount: 100,
+                max_assets_in: vec![],
+            }));
+            fund.record_positions(&RuntimeCall::Swaps(SwapsCall::SwapExactAmountOut {
+                pool_id: 1,
+                asset_in: ZeitgeistAsset::Ztg,
+                max_asset_amount_in: None,
+                asset_out: outcome.clone(),
+                asset_amount_out: 40,
+                max_price: None,
+            }));
+            fund.record_positions(&RuntimeCall::PredictionMarkets(
+                PredictionMarketsCall::BuyCompleteSet {
+                    market_id: 3,
+                    amount: 200,
+                },
+            ));
+            ink::env::test::set_account_balance::<Environment>(contract_id, 600);
+
+            // Complete sets are paid out as ZTG
+            assert_eq!(
+                fund.redeemable_in_kind(500),
+                (
+                    400,
+                    vec![(ZeitgeistAsset::PoolShare(1), 50), (outcome.clone(), 20)]
+                )
+            );
+            assert_eq!(fund.redeemable_in_kind(0), (0, vec![]));
+        }
+
+        #[ink::test]
+        fn redeem_in_kind_burns_shares() {
+            let manager = AccountId::from([0x01; 32]);
+            let user = AccountId::from([0x04; 32]);
+            let mut fund = create_funded_fund(manager, user);
+
+            // The manager's shares are locked until they vest
+            ink::env::test::set_caller::<Environment>(manager);
+            assert_eq!(fund.redeem_in_kind(250), Err(Error::ManagerSharesAreLocked));
+
+            ink::env::test::set_caller::<Environment>(user);
+            assert_eq!(fund.redeem_in_kind(0), Err(Error::ZeroShares));
+            assert_eq!(fund.redeem_in_kind(800), Err(Error::InsufficientBalance));
+            let user_balance = ink::env::test::get_account_balance::<Environment>(user).unwrap();
+            assert_eq!(fund.redeem_in_kind(500), Ok(500));
+            assert_eq!(fund.balance_of(user), 250);
+            assert_eq!(fund.balance_of(AccountId::from([0; 32])), 500);
+            assert_eq!(
+                ink::env::test::get_account_balance::<Environment>(user),
+                Ok(user_balance + 500)
+            );
+        }
+
+        #[ink::test]
+        fn dividends_after_in_kind_redemption_are_fully_claimable() {
+            let manager = AccountId::from([0x01; 32]);
+            let user = AccountId::from([0x04; 32]);
+            let mut fund = create_funded_fund(manager, user);
+
+            ink::env::test::set_caller::<Environment>(user);
+            fund.redeem_in_kind(500).unwrap();
+
+            // The burned shares don't dilute the dividend
+            fund.record_dividend(ZeitgeistAsset::Ztg, 500);
+            assert_eq!(fund.calc_dividend(manager), 250);
+            assert_eq!(fund.calc_dividend(user), 250);
+        }
+
+        #[ink::test]
+        fn risk_limits_are_enforced() {
+            let manager = AccountId::from([0x01; 32]);
+            let user = AccountId::from([0x04; 32]);
+            let mut fund = create_funded_fund(manager, user);
+            fund.risk_limits = RiskLimits {
+                allowed_markets: vec![1, 2],
+                allowed_pools: vec![1],
+                max_market_exposure_bps: 2_000,
+                max_total_exposure_bps: 3_000,
+            };
+            let buy = |market_id, amount| {
+                RuntimeCall::PredictionMarkets(PredictionMarketsCall::BuyCompleteSet {
+                    market_id,
+                    amount,
+                })
+            };
+
+            assert_eq!(
+                fund.check_risk_limits(&buy(3, 1)),
+                Err(Error::MarketNotAllowed)
+            );
+            assert_eq!(
+                fund.check_risk_limits(&RuntimeCall::Swaps(SwapsCall::PoolJoin {
+                    pool_id: 2,
+                    pool_amount: 1,
+                    max_assets_in: vec![1],
+                })),
+                Err(Error::PoolNotAllowed)
+            );
+
+            // 20% of the 1000 ZTG fund can go into one market
+            assert_eq!(
+                fund.check_risk_limits(&buy(1, 201)),
+                Err(Error::MarketExposureTooHigh)
+            );
+            assert_eq!(fund.check_risk_limits(&buy(1, 200)), Ok(()));
+            fund.record_exposure(&buy(1, 200));
+            assert_eq!(fund.market_exposure(1), 200);
+
+            // 30% can go into markets in total
+            assert_eq!(
+                fund.check_risk_limits(&buy(2, 101)),
+                Err(Error::TotalExposureTooHigh)
+            );
+            fund.record_exposure(&buy(2, 100));
+            assert_eq!(fund.total_exposure(), 300);
+
+            // A swap without a maximum input counts as the whole fund
+            assert_eq!(
+                fund.check_risk_limits(&RuntimeCall::Swaps(SwapsCall::SwapExactAmountOut {
+                    pool_id: 1,
+                    asset_in: ZeitgeistAsset::Ztg,
+                    max_asset_amount_in: None,
+                    asset_out: ZeitgeistAsset::CategoricalOutcome(2, 0),
+                    asset_amount_out: 1,
+                    max_price: None,
+                })),
+                Err(Error::MarketExposureTooHigh)
+            );
+
+            // Exits release exposure and are always allowed
+            let sell = RuntimeCall::PredictionMarkets(PredictionMarketsCall::SellCompleteSet {
+                market_id: 1,
+                amount: 150,
+            });
+            assert_eq!(fund.check_risk_limits(&sell), Ok(()));
+            fund.record_exposure(&sell);
+            assert_eq!(fund.market_exposure(1), 50);
+            assert_eq!(fund.total_exposure(), 150);
+            fund.record_exposure(&RuntimeCall::PredictionMarkets(
+                PredictionMarketsCall::RedeemShares { market_id: 2 },
+            ));
+            assert_eq!(fund.market_exposure(2), 0);
+            assert_eq!(fund.total_exposure(), 50);
+
+            // Limits follow the fund's size once a NAV is reported
+            ink::env::test::set_caller::<Environment>(manager);
+            fund.report_nav(2_000).unwrap();
+            assert_eq!(fund.check_risk_limits(&buy(1, 350)), Ok(()));
+            assert_eq!(
+                fund.check_risk_limits(&buy(1, 351)),
+                Err(Error::MarketExposureTooHigh)
+            );
+            fund.report_nav(500).unwrap();
+            assert_eq!(
+                fund.check_risk_limits(&buy(1, 51)),
+                Err(Error::MarketExposureTooHigh)
+            );
+        }
+
+        #[ink::test]
+        fn pool_exits_release_exposure() {
+            let manager = AccountId::from([0x01; 32]);
+            let user = AccountId::from([0x04; 32]);
+            let mut fund = create_funded_fund(manager, user);
+
+            let join = RuntimeCall::Swaps(SwapsCall::PoolJoin {
+                pool_id: 1,
+                pool_amount: 100,
+                max_assets_in: vec![100, 100],
+            });
+            fund.record_exposure(&join);
+            fund.record_positions(&join);
+            assert_eq!(fund.total_exposure(), 200);
+
+            let exit = RuntimeCall::Swaps(SwapsCall::PoolExit {
+                pool_id: 1,
+                pool_amount: 25,
+                min_assets_out: vec![],
+            });
+            fund.record_exposure(&exit);
+            fund.record_positions(&exit);
+            assert_eq!(fund.total_exposure(), 150);
+        }
+
+        #[ink::test]
+        fn risk_limits_are_updated_by_shareholders() {
+            let manager = AccountId::from([0x01; 32]);
+            let user = AccountId::from([0x04; 32]);
+            let mut fund = create_funded_fund(manager, user);
+            let limits = RiskLimits {
+                allowed_markets: vec![42],
+                allowed_pools: vec![],
+                max_market_exposure_bps: 1_000,
+                max_total_exposure_bps: 5_000,
+            };
+
+            ink::env::test::set_block_timestamp::<Environment>(10);
+            ink::env::test::set_caller::<Environment>(user);
+            assert_eq!(
+                fund.propose_risk_limits(RiskLimits {
+                    max_total_exposure_bps: 10_001,
+                    ..limits.clone()
+                }),
+                Err(Error::InvalidRiskLimits)
+            );
+            let id = fund.propose_risk_limits(limits.clone()).unwrap();
+            assert_eq!(fund.vote_manager_replacement(id), Ok(750));
+
+            ink::env::test::set_block_timestamp::<Environment>(10 + MANAGER_VOTE_TIMELOCK);
+            assert_eq!(fund.execute_manager_replacement(id), Ok(()));
+            assert_eq!(fund.risk_limits(), limits);
+            assert_eq!(fund.manager(), manager);
+        }
+
+        #[ink::test]
+        fn timelock_is_updated_by_shareholders() {
+            let manager = AccountId::from([0x01; 32]);
+            let user = AccountId::from([0x04; 32]);
+            let mut fund = create_funded_fund(manager, user);
+            assert_eq!(fund.timelock(), TimelockConfig::default());
+            let timelock = TimelockConfig {
+                threshold: 500,
+                delay: 2_000,
+            };
+
+            ink::env::test::set_block_timestamp::<Environment>(10);
+            ink::env::test::set_caller::<Environment>(user);
+            let id = fund.propose_timelock(timelock.clone()).unwrap();
+            assert_eq!(fund.vote_manager_replacement(id), Ok(750));
+
+            ink::env::test::set_block_timestamp::<Environment>(10 + MANAGER_VOTE_TIMELOCK);
+            assert_eq!(fund.execute_manager_replacement(id), Ok(()));
+            assert_eq!(fund.timelock(), timelock);
+            assert_eq!(fund.risk_limits(), open_mandate());
+        }
+
+        #[ink::test]
+        fn allowance_helpers_work() {
+            let manager = AccountId::from([0x01; 32]);
+            let user = AccountId::from([0x04; 32]);
+            let mut fund = create_funded_fund(manager, user);
+
+            ink::env::test::set_caller::<Environment>(user);
+            fund.increase_allowance(manager, 100).unwrap();
+            fund.increase_allowance(manager, 50).unwrap();
+            assert_eq!(fund.allowance(user, manager), 150);
+            assert_eq!(
+                fund.decrease_allowance(manager, 151),
+                Err(PSP22Error::InsufficientAllowance)
+            );
+            fund.decrease_allowance(manager, 50).unwrap();
+            assert_eq!(fund.allowance(user, manager), 100);
+
+            ink::env::test::set_caller::<Environment>(manager);
+            assert_eq!(
+                fund.transfer_from(user, manager, 101, vec![]),
+                Err(PSP22Error::InsufficientAllowance)
+            );
+            assert_eq!(
+                fund.transfer_from(user, AccountId::from([0; 32]), 100, vec![]),
+                Err(PSP22Error::ZeroRecipientAddress)
+            );
+            fund.transfer_from(user, manager, 100, vec![]).unwrap();
+            assert_eq!(fund.allowance(user, manager), 0);
+            assert_eq!(fund.balance_of(manager), 350);
+
+            // Spending an allowance announces the allowance that is left
+            type Event = <ZeitFund as ink::reflect::ContractEventBase>::Type;
+            let event = ink::env::test::recorded_events().last().unwrap();
+            match <Event as scale::Decode>::decode(&mut &event.data[..]).unwrap() {
+                Event::Approval(super::Approval {
+                    owner,
+                    spender,
+                    value,
+                }) => assert_eq!((owner, spender, value), (user, manager, 0)),
+                _ => panic!("expected an Approval event"),
+            }
+        }
+
+        #[ink::test]
+        fn token_metadata_works() {
+            let manager = AccountId::from([0x01; 32]);
+            let fund = ZeitFund::no_instantiation(
+                manager,
+                test_guardian(),
+                TokenMetadata {
+                    name: Some("Zeit Fund".into()),
+                    symbol: Some("ZF".into()),
+                },
+                1_000,
+                None,
+                FUNDING_DEADLINE,
+                MATURITY,
+                FeeConfig::default(),
+                open_mandate(),
+                TimelockConfig::default(),
+                None,
+                manager,
+            );
+            assert_eq!(fund.token_name(), Some("Zeit Fund".into()));
+            assert_eq!(fund.token_symbol(), Some("ZF".into()));
+            assert_eq!(fund.token_decimals(), 10);
+        }
+
+        #[ink::test]
+        fn env_errors_do_not_panic() {
+            use ink::env::Error as EnvError;
+
+            assert_eq!(
+                Error::from(EnvError::CalleeTrapped),
+                Error::Env(EnvFailure::CalleeTrapped)
+            );
+            assert_eq!(
+                Error::from(EnvError::CallRuntimeFailed),
+                Error::Env(EnvFailure::CallRuntimeFailed)
+            );
+            assert_eq!(
+                PSP22Error::from(Error::Env(EnvFailure::KeyNotFound)),
+                PSP22Error::Custom("Env".into())
+            );
+        }
+
+        #[ink::test]
+        fn dividend_wallet_addresses_are_unique() {
+            let wallet_code_hash = Hash::from([0x02; 32]);
+            let fund = AccountId::from([0xAA; 32]);
+            let other_fund = AccountId::from([0xBB; 32]);
+
+            let wallet = predict_dividend_wallet_address(&fund, &wallet_code_hash);
+            assert_eq!(
+                wallet,
+                predict_dividend_wallet_address(&fund, &wallet_code_hash)
+            );
+            assert_ne!(
+                wallet,
+                predict_dividend_wallet_address(&other_fund, &wallet_code_hash)
+            );
+            assert_ne!(
+                wallet,
+                predict_dividend_wallet_address(&fund, &Hash::from([0x03; 32]))
+            );
+
+            // The salt is the fund's address
+            assert_eq!(
+                wallet,
+                contract_address(
+                    &fund,
+                    &wallet_code_hash,
+                    &[0x9b, 0xae, 0x9d, 0x5e],
+                    &[0xAA; 32]
+                )
+            );
+        }
+
+        #[ink::test]
+        fn balance_checkpoints_work() {
+            let manager = AccountId::from([0x01; 32]);
+            let user = AccountId::from([0x04; 32]);
+            let zero = AccountId::from([0; 32]);
+            ink::env::test::set_block_timestamp::<Environment>(10);
+            let mut fund = create_fund_no_wallet(manager, 1_000, false);
+            megafund_wallet(manager);
+            megafund_wallet(user);
+
+            // Funding at 20 & 30
+            ink::env::test::set_block_timestamp::<Environment>(20);
+            ink::env::pay_with_call!(fund.fund(), 400).unwrap();
+            ink::env::test::set_block_timestamp::<Environment>(30);
+            ink::env::test::set_caller::<Environment>(user);
+            ink::env::pay_with_call!(fund.fund(), 600).unwrap();
+
+            // Multiple transfers at 40 only keep the last balance
+            ink::env::test::set_block_timestamp::<Environment>(40);
+            fund.transfer(manager, 100, vec![]).unwrap();
+            fund.transfer(manager, 100, vec![]).unwrap();
+
+            assert_eq!(fund.balance_of_at(manager, 19), 0);
+            assert_eq!(fund.balance_of_at(manager, 20), 400);
+            assert_eq!(fund.balance_of_at(manager, 39), 400);
+            assert_eq!(fund.balance_of_at(manager, 40), 600);
+            assert_eq!(fund.balance_of_at(manager, u64::MAX), 600);
+            assert_eq!(fund.balance_of_at(user, 29), 0);
+            assert_eq!(fund.balance_of_at(user, 30), 600);
+            assert_eq!(fund.balance_of_at(user, 40), 400);
+            assert_eq!(fund.balance_of_at(zero, 9), 0);
+            assert_eq!(fund.balance_of_at(zero, 10), 1_000);
+            assert_eq!(fund.balance_of_at(zero, 25), 600);
+            assert_eq!(fund.balance_of_at(zero, 30), 0);
+
+            assert_eq!(fund.total_supply_at(9), 0);
+            assert_eq!(fund.total_supply_at(10), 1_000);
+            assert_eq!(fund.total_supply_at(40), 1_000);
+        }
+
+        #[ink::test]
+        fn proposing_manager_requires_shares() {
+            let manager = AccountId::from([0x01; 32]);
+            let user = AccountId::from([0x04; 32]);
+            let outsider = AccountId::from([0x05; 32]);
+            let mut fund = create_funded_fund(manager, user);
+
+            ink::env::test::set_caller::<Environment>(outsider);
+            assert_eq!(
+                fund.propose_manager_replacement(outsider),
+                Err(Error::NoVotingPower)
+            );
+
+            ink::env::test::set_caller::<Environment>(user);
+            assert_eq!(fund.propose_manager_replacement(outsider), Ok(0));
+            assert_eq!(fund.manager_proposal_count(), 1);
+            let proposal = fund.manager_proposal(0).unwrap();
+            assert_eq!(proposal.proposer, user);
+            assert_eq!(proposal.action, ProposalAction::ReplaceManager(outsider));
+            assert_eq!(fund.manager_proposal(1), None);
+        }
+
+        #[ink::test]
+        fn manager_replacement_works() {
+            let manager = AccountId::from([0x01; 32]);
+            let user = AccountId::from([0x04; 32]);
+            let new_manager = AccountId::from([0x05; 32]);
+            let mut fund = create_funded_fund(manager, user);
+
+            ink::env::test::set_block_timestamp::<Environment>(10);
+            ink::env::test::set_caller::<Environment>(user);
+            let id = fund.propose_manager_replacement(new_manager).unwrap();
+
+            // The manager's quarter of the shares doesn't reach quorum
+            ink::env::test::set_caller::<Environment>(manager);
+            assert_eq!(fund.vote_manager_replacement(id), Ok(250));
+            assert_eq!(fund.vote_manager_replacement(id), Err(Error::AlreadyVoted));
+            assert!(fund.has_voted(id, manager));
+            assert_eq!(
+                fund.execute_manager_replacement(id),
+                Err(Error::QuorumNotReached)
+            );
+
+            // The user's votes reach quorum, but the timelock must pass
+            ink::env::test::set_caller::<Environment>(user);
+            assert_eq!(fund.vote_manager_replacement(id), Ok(750));
+            assert_eq!(
+                fund.execute_manager_replacement(id),
+                Err(Error::TimelockNotOver)
+            );
+
+            ink::env::test::set_block_timestamp::<Environment>(10 + MANAGER_VOTE_TIMELOCK);
+            assert_eq!(fund.execute_manager_replacement(id), Ok(()));
+            assert_eq!(fund.manager(), new_manager);
+            assert_eq!(
+                fund.execute_manager_replacement(id),
+                Err(Error::ProposalAlreadyExecuted)
+            );
+
+            // The old manager no longer has manager powers
+            ink::env::test::set_caller::<Environment>(manager);
+            assert_eq!(fund.report_nav(1_000), Err(Error::OnlyManagerAllowed));
+            ink::env::test::set_caller::<Environment>(new_manager);
+            assert_eq!(fund.report_nav(1_000), Ok(()));
+        }
+
+        #[ink::test]
+        fn manager_votes_use_snapshot_balances() {
+            let manager = AccountId::from([0x01; 32]);
+            let user = AccountId::from([0x04; 32]);
+            let other = AccountId::from([0x05; 32]);
+            let mut fund = create_funded_fund(manager, user);
+
+            ink::env::test::set_block_timestamp::<Environment>(10);
+            ink::env::test::set_caller::<Environment>(manager);
+            let id = fund.propose_manager_replacement(other).unwrap();
+
+            // Shares transferred after the proposal cannot be used to vote again
+            ink::env::test::set_caller::<Environment>(user);
+            assert_eq!(fund.vote_manager_replacement(id), Ok(750));
+            fund.transfer(other, 750, vec![]).unwrap();
+            ink::env::test::set_caller::<Environment>(other);
+            assert_eq!(fund.vote_manager_replacement(id), Err(Error::NoVotingPower));
+
+            // A later proposal sees the new balances
+            ink::env::test::set_block_timestamp::<Environment>(20);
+            let id = fund.propose_manager_replacement(other).unwrap();
+            assert_eq!(fund.vote_manager_replacement(id), Ok(750));
+            ink::env::test::set_caller::<Environment>(user);
+            assert_eq!(fund.vote_manager_replacement(id), Err(Error::NoVotingPower));
+        }
+
+        /// Creates a funded fund that queues actions moving more than 100 ZTG for 1000 ms.
+        fn create_timelocked_fund(manager: AccountId, user: AccountId) -> ZeitFund {
+            let mut fund = create_funded_fund(manager, user);
+            fund.timelock.set(&TimelockConfig {
+                threshold: 100,
+                delay: 1_000,
+            });
+            ink::env::test::set_block_timestamp::<Environment>(10);
+            fund
+        }
+
+        #[ink::test]
+        fn large_manager_actions_are_queued() {
+            let manager = AccountId::from([0x01; 32]);
+            let user = AccountId::from([0x04; 32]);
+            let mut fund = create_timelocked_fund(manager, user);
+            let buy = PredictionMarketsCall::BuyCompleteSet {
+                market_id: 1,
+                amount: 500,
+            };
+
+            ink::env::test::set_caller::<Environment>(manager);
+            assert_eq!(fund.prediction_market_call(buy.clone()), Ok(()));
+            assert_eq!(fund.issue_dividend(ZeitgeistAsset::Ztg, 101), Ok(()));
+            assert_eq!(fund.queued_action_count(), 2);
+            assert_eq!(
+                fund.queued_action(0),
+                Some(QueuedAction {
+                    call: ManagerCall::PredictionMarket(buy),
+                    ztg: 500,
+                    eta: 1_010,
+                    snapshot: 9,
+                    vetoes: 0,
+                    status: ActionStatus::Queued,
+                })
+            );
+            assert_eq!(
+                fund.queued_action(1).unwrap().call,
+                ManagerCall::IssueDividend(ZeitgeistAsset::Ztg, 101)
+            );
+            assert_eq!(fund.queued_actions(1, 10).len(), 1);
+            assert_eq!(fund.pending_actions().len(), 2);
+
+            // Nothing is sent or recorded until the action is executed
+            assert_eq!(fund.market_exposure(1), 0);
+            assert_eq!(fund.dividend_assets(), []);
+
+            // Calls outside of the mandate are not queued
+            assert_eq!(
+                fund.prediction_market_call(PredictionMarketsCall::BuyCompleteSet {
+                    market_id: 42,
+                    amount: 500,
+                }),
+                Err(Error::MarketNotAllowed)
+            );
+            assert_eq!(fund.queued_action_count(), 2);
+
+            // Only the manager can execute, after the delay
+            assert_eq!(fund.execute_action(0), Err(Error::TimelockNotOver));
+            assert_eq!(fund.execute_action(2), Err(Error::ActionNotFound));
+            ink::env::test::set_block_timestamp::<Environment>(1_010);
+            ink::env::test::set_caller::<Environment>(user);
+            assert_eq!(
+                fund.execute_action(0),
+                Err(Error::MissingRole(Role::Trader))
+            );
+        }
+
+        #[ink::test]
+        fn shareholders_can_veto_queued_actions() {
+            let manager = AccountId::from([0x01; 32]);
+            let user = AccountId::from([0x04; 32]);
+            let outsider = AccountId::from([0x05; 32]);
+            let mut fund = create_timelocked_fund(manager, user);
+
+            ink::env::test::set_caller::<Environment>(manager);
+            fund.issue_dividend(ZeitgeistAsset::Ztg, 500).unwrap();
+
+            // The manager's quarter of the shares is not enough to veto
+            assert_eq!(fund.veto_action(0), Ok(250));
+            assert_eq!(fund.veto_action(0), Err(Error::AlreadyVoted));
+            assert_eq!(fund.queued_action(0).unwrap().status, ActionStatus::Queued);
+
+            ink::env::test::set_caller::<Environment>(outsider);
+            assert_eq!(fund.veto_action(0), Err(Error::NoVotingPower));
+
+            ink::env::test::set_caller::<Environment>(user);
+            assert_eq!(fund.veto_action(0), Ok(750));
+            assert!(fund.has_vetoed(0, user));
+            let action = fund.queued_action(0).unwrap();
+            assert_eq!(action.status, ActionStatus::Vetoed);
+            assert_eq!(action.vetoes, 1_000);
+            assert_eq!(fund.pending_actions(), []);
+
+            // A vetoed action can't be executed or vetoed again
+            ink::env::test::set_block_timestamp::<Environment>(1_010);
+            ink::env::test::set_caller::<Environment>(manager);
+            assert_eq!(fund.execute_action(0), Err(Error::ActionNotQueued));
+            ink::env::test::set_caller::<Environment>(outsider);
+            assert_eq!(fund.veto_action(0), Err(Error::ActionNotQueued));
+        }
+
+        /// Creates a funded open-ended fund with a window of 100 ms every 1000 ms.
+        fn create_open_ended_fund(manager: AccountId, user: AccountId) -> ZeitFund {
+            let mut fund = create_funded_fund(manager, user);
+            fund.open_ended.set(&OpenEndedConfig {
+                nav_oracle: Some(AccountId::from([0x0B; 32])),
+                window_interval: 1_000,
+                window_length: 100,
+                subscription_cap: 1_000,
+                redemption_gate_bps: 1_000,
+            });
+            fund
+        }
+
+        #[ink::test]
+        fn subscriptions_are_settled_at_the_next_nav_strike() {
+            let manager = AccountId::from([0x01; 32]);
+            let user = AccountId::from([0x04; 32]);
+            let buyer = AccountId::from([0x05; 32]);
+            let oracle = AccountId::from([0x0B; 32]);
+
+            // Closed-ended funds don't take subscriptions after the raise
+            let mut fund = create_funded_fund(manager, user);
+            ink::env::test::set_caller::<Environment>(user);
+            assert_eq!(
+                ink::env::pay_with_call!(fund.fund(), 100),
+                Err(Error::NotAllowedInState(FundState::Active))
+            );
+            assert_eq!(fund.request_redemption(100), Err(Error::NotOpenEnded));
+
+            let mut fund = create_open_ended_fund(manager, user);
+            megafund_wallet(buyer);
+            ink::env::test::set_caller::<Environment>(buyer);
+            ink::env::test::set_block_timestamp::<Environment>(50);
+            assert!(fund.is_window_open());
+            assert_eq!(ink::env::pay_with_call!(fund.fund(), 600), Ok(()));
+            assert_eq!(
+                ink::env::pay_with_call!(fund.fund(), 500),
+                Err(Error::SubscriptionCapReached)
+            );
+            assert_eq!(fund.subscription_requests(), [(buyer, 600)]);
+
+            // Requests are only taken during a window, and each window has its own cap
+            ink::env::test::set_block_timestamp::<Environment>(150);
+            assert!(!fund.is_window_open());
+            assert_eq!(
+                ink::env::pay_with_call!(fund.fund(), 100),
+                Err(Error::WindowClosed)
+            );
+            ink::env::test::set_block_timestamp::<Environment>(1_050);
+            assert_eq!(ink::env::pay_with_call!(fund.fund(), 400), Ok(()));
+            assert_eq!(fund.pending_subscriptions(), 1_000);
+            assert_eq!(fund.balance_of(buyer), 0);
+
+            assert_eq!(fund.report_nav(2_000), Err(Error::OnlyManagerAllowed));
+
+            // The oracle strikes a NAV of 2 ZTG per share
+            ink::env::test::set_caller::<Environment>(oracle);
+            assert_eq!(fund.report_nav(2_000), Ok(()));
+            assert_eq!(fund.balance_of(buyer), 500);
+            assert_eq!(fund.total_supply(), 1_500);
+            assert_eq!(fund.nav(), 3_000);
+            assert_eq!(fund.pending_subscriptions(), 0);
+            assert_eq!(fund.subscription_requests(), []);
+        }
+
+        #[ink::test]
+        fn settling_a_subscription_that_overflows_fails() {
+            let manager = AccountId::from([0x01; 32]);
+            let user = AccountId::from([0x04; 32]);
+            let buyer = AccountId::from([0x05; 32]);
+            let mut fund = create_open_ended_fund(manager, user);
+
+            // The subscription is worth more shares than fit in a `Balance`
+            let amount = u128::MAX / 2;
+            fund.subscription_queue.insert(0, &(buyer, amount));
+            fund.subscription_queue_end.set(&1);
+            fund.pending_subscriptions.set(&amount);
+
+            ink::env::test::set_caller::<Environment>(manager);
+            assert_eq!(fund.report_nav(2_000), Err(Error::Overflow));
+            assert_eq!(fund.balance_of(buyer), 0);
+        }
+
+        #[ink::test]
+        fn redemptions_are_gated_and_cancelled_on_liquidation() {
+            let manager = AccountId::from([0x01; 32]);
+            let user = AccountId::from([0x04; 32]);
+            let contract_id = AccountId::from([0xAA; 32]);
+            let mut fund = create_open_ended_fund(manager, user);
+            ink::env::test::set_block_timestamp::<Environment>(50);
+
+            ink::env::test::set_caller::<Environment>(manager);
+            assert_eq!(
+                fund.request_redemption(10),
+                Err(Error::ManagerSharesAreLocked)
+            );
+
+            ink::env::test::set_caller::<Environment>(user);
+            assert_eq!(fund.request_redemption(0), Err(Error::ZeroShares));
+            assert_eq!(fund.request_redemption(300), Ok(()));
+            assert_eq!(fund.balance_of(user), 450);
+            assert_eq!(fund.balance_of(contract_id), 300);
+            let user_balance = ink::env::test::get_account_balance::<Environment>(user).unwrap();
+
+            // Only 10% of the shares can be redeemed per strike
+            ink::env::test::set_caller::<Environment>(manager);
+            fund.report_nav(2_000).unwrap();
+            assert_eq!(
+                ink::env::test::get_account_balance::<Environment>(user),
+                Ok(user_balance + 200)
+            );
+            assert_eq!(fund.redemption_requests(), [(user, 200)]);
+            assert_eq!(fund.balance_of(AccountId::from([0; 32])), 100);
+            assert_eq!(fund.nav(), 1_800);
+
+            fund.report_nav(1_800).unwrap();
+            assert_eq!(
+                ink::env::test::get_account_balance::<Environment>(user),
+                Ok(user_balance + 380)
+            );
+            assert_eq!(fund.pending_redemptions(), 110);
+
+            // Liquidation returns the shares that are still queued
+            ink::env::test::set_block_timestamp::<Environment>(MATURITY);
+            fund.liquidate().unwrap();
+            assert_eq!(fund.balance_of(user), 560);
+            assert_eq!(fund.balance_of(contract_id), 0);
+            assert_eq!(fund.redemption_requests(), []);
+        }
+
+        #[ink::test]
+        fn queued_redemptions_earn_no_dividends() {
+            let manager = AccountId::from([0x01; 32]);
+            let user = AccountId::from([0x04; 32]);
+            let contract_id = AccountId::from([0xAA; 32]);
+            let mut fund = create_open_ended_fund(manager, user);
+            ink::env::test::set_block_timestamp::<Environment>(50);
+
+            ink::env::test::set_caller::<Environment>(user);
+            fund.request_redemption(300).unwrap();
+
+            // The escrowed shares are left out, so the holders can claim the whole dividend
+            fund.record_dividend(ZeitgeistAsset::Ztg, 700);
+            assert_eq!(fund.calc_dividend(manager), 250);
+            assert_eq!(fund.calc_dividend(user), 450);
+            assert_eq!(fund.calc_dividend(contract_id), 0);
+
+            // Shares returned from escrow don't bring a dividend with them
+            ink::env::test::set_caller::<Environment>(manager);
+            ink::env::test::set_block_timestamp::<Environment>(MATURITY);
+            fund.liquidate().unwrap();
+            assert_eq!(fund.balance_of(user), 750);
+            assert_eq!(fund.calc_dividend(user), 450);
+        }
+
+        #[ink::test]
+        fn new_funds_use_the_latest_storage_version() {
+            let fund = create_fund_no_wallet(AccountId::from([0x01; 32]), 1_000, false);
+            assert_eq!(fund.storage_version(), STORAGE_VERSION);
+        }
+
+        #[ink::test]
+        fn migrate_moves_v1_dividend_assets() {
+            let manager = AccountId::from([0x01; 32]);
+            let user = AccountId::from([0x04; 32]);
+            let buyer = AccountId::from([0x05; 32]);
+            let foreign = ZeitgeistAsset::ForeignAsset(1);
+            let mut fund = create_funded_fund(manager, user);
+
+            // A version 1 fund kept its dividend assets in a vector
+            fund.storage_version.set(&1);
+            fund.dividend_assets = vec![
+                LegacyZeitgeistAsset::Ztg,
+                LegacyZeitgeistAsset::PoolShare,
+                LegacyZeitgeistAsset::ForeignAsset(1),
+            ];
+            fund.dividends_per_share
+                .insert(ZeitgeistAsset::Ztg, &DIVIDEND_PRECISION);
+            fund.dividends_per_share
+                .insert(&foreign, &(2 * DIVIDEND_PRECISION));
+
+            // The fund can't be used until it is migrated
+            ink::env::test::set_caller::<Environment>(user);
+            assert_eq!(
+                fund.transfer(buyer, 750, vec![]),
+                Err(PSP22Error::Custom("MigrationRequired".into()))
+            );
+
+            // Anyone can migrate it, once
+            ink::env::test::set_caller::<Environment>(buyer);
+            assert_eq!(fund.migrate(), Ok(STORAGE_VERSION));
+            assert_eq!(fund.storage_version(), STORAGE_VERSION);
+            assert!(fund.dividend_assets.is_empty());
+            assert_eq!(
+                fund.dividend_assets(),
+                [ZeitgeistAsset::Ztg, foreign.clone()]
+            );
+            assert_eq!(fund.migrate(), Err(Error::AlreadyMigrated));
+
+            // Transfers settle the migrated assets
+            ink::env::test::set_caller::<Environment>(user);
+            fund.transfer(buyer, 750, vec![]).unwrap();
+            assert_eq!(fund.calc_dividend(user), 750);
+            assert_eq!(fund.calc_asset_dividend(user, foreign.clone()), 1_500);
+            assert_eq!(fund.calc_asset_dividend(buyer, foreign), 0);
+
+            // New dividends are added after the migrated assets
+            let outcome = ZeitgeistAsset::CategoricalOutcome(7, 1);
+            fund.record_dividend(outcome.clone(), 100);
+            assert_eq!(
+                fund.dividend_assets(),
+                [
+                    ZeitgeistAsset::Ztg,
+                    ZeitgeistAsset::ForeignAsset(1),
+                    outcome
+                ]
+            );
+        }
+
+        #[ink::test]
+        fn migrate_moves_v2_positions() {
+            let manager = AccountId::from([0x01; 32]);
+            let user = AccountId::from([0x04; 32]);
+            let mut fund = create_funded_fund(manager, user);
+
+            // A version 2 fund kept its positions in a vector
+            fund.storage_version.set(&2);
+            fund.positions = vec![
+                LegacyPosition::Market(7),
+                LegacyPosition::Pool(3),
+                LegacyPosition::Asset(LegacyZeitgeistAsset::PoolShare),
+                LegacyPosition::Asset(LegacyZeitgeistAsset::CategoricalOutcome(7, 1)),
+            ];
+
+            assert_eq!(fund.migrate(), Ok(STORAGE_VERSION));
+            assert!(fund.positions.is_empty());
+            assert_eq!(
+                fund.positions(),
+                [
+                    Position::Market(7),
+                    Position::Pool(3),
+                    Position::Asset(ZeitgeistAsset::CategoricalOutcome(7, 1)),
+                ]
+            );
+
+            // Closing a position moves the last one into its place
+            fund.close_positions(|p| p == &Position::Market(7));
+            assert_eq!(
+                fund.positions(),
+                [
+                    Position::Asset(ZeitgeistAsset::CategoricalOutcome(7, 1)),
+                    Position::Pool(3),
+                ]
+            );
+            fund.open_position(Position::Market(7));
+            fund.open_position(Position::Pool(3));
+            assert_eq!(fund.markets(), [7]);
+            assert_eq!(fund.positions().len(), 3);
+        }
+
+        #[ink::test]
+        fn migrate_loads_a_v2_fund_from_storage() {
+            /// The fields that version 2 stored with the root of the contract, in order.
+            #[derive(scale::Encode, scale::Decode)]
+            struct V2Root {
+                manager: AccountId,
+                metadata: TokenMetadata,
+                total_supply: u128,
+                funding_amount: u128,
+                funding_goal: u128,
+                funding_deadline: u64,
+                maturity: u64,
+                state: FundState,
+                guardian: AccountId,
+                paused_from: FundState,
+                lock_manager_shares: bool,
+                dividend_wallet: AccountId,
+                dividend_assets: Vec<LegacyZeitgeistAsset>,
+                supply_checkpoint_count: u32,
+                manager_proposal_count: u32,
+                fees: FeeConfig,
+                nav: u128,
+                high_water_mark: u128,
+                management_fee_accrued_at: u64,
+                performance_fee_crystallised_at: u64,
+                positions: Vec<LegacyPosition>,
+                risk_limits: RiskLimits,
+                total_exposure: u128,
+            }
+
+            let manager = AccountId::from([0x01; 32]);
+            let user = AccountId::from([0x04; 32]);
+            let buyer = AccountId::from([0x05; 32]);
+            let fund = create_funded_fund(manager, user);
+            let risk_limits = RiskLimits {
+                allowed_markets: vec![7],
+                allowed_pools: vec![3],
+                max_market_exposure_bps: 2_000,
+                max_total_exposure_bps: 5_000,
+            };
+
+            // Overwrite the root with the layout that version 2 wrote
+            let root = V2Root {
+                manager,
+                metadata: fund.metadata.clone(),
+                total_supply: fund.total_supply,
+                funding_amount: fund.funding_amount,
+                funding_goal: fund.funding_goal,
+                funding_deadline: fund.funding_deadline,
+                maturity: fund.maturity,
+                state: fund.state,
+                guardian: fund.guardian,
+                paused_from: fund.paused_from,
+                lock_manager_shares: false,
+                dividend_wallet: fund.dividend_wallet(),
+                dividend_assets: vec![],
+                supply_checkpoint_count: fund.supply_checkpoint_count,
+                manager_proposal_count: fund.manager_proposal_count,
+                fees: fund.fees.clone(),
+                nav: fund.nav,
+                high_water_mark: fund.high_water_mark,
+                management_fee_accrued_at: fund.management_fee_accrued_at,
+                performance_fee_crystallised_at: fund.performance_fee_crystallised_at,
+                positions: vec![
+                    LegacyPosition::Market(7),
+                    LegacyPosition::Asset(LegacyZeitgeistAsset::PoolShare),
+                ],
+                risk_limits: risk_limits.clone(),
+                total_exposure: 0,
+            };
+            ink::env::set_contract_storage(&<ZeitFund as StorageKey>::KEY, &root);
+            let wallet = fund.dividend_wallet();
+            drop(fund);
+
+            // The current code reads it, and the fund can be used once it is migrated
+            let mut fund =
+                ink::env::get_contract_storage::<_, ZeitFund>(&<ZeitFund as StorageKey>::KEY)
+                    .unwrap()
+                    .unwrap();
+            fund.storage_version.set(&2);
+            assert_eq!(fund.manager(), manager);
+            assert_eq!(fund.dividend_wallet(), wallet);
+            assert_eq!(fund.risk_limits(), risk_limits);
+            assert_eq!(fund.total_supply(), 1_000);
+
+            // Every byte of it is read, and written back the same way
+            let mut encoded = Vec::new();
+            ink::storage::traits::Storable::encode(&fund, &mut encoded);
+            assert_eq!(encoded, scale::Encode::encode(&root));
+
+            ink::env::test::set_caller::<Environment>(buyer);
+            assert_eq!(fund.migrate(), Ok(STORAGE_VERSION));
+            assert_eq!(fund.positions(), [Position::Market(7)]);
+            assert_eq!(fund.risk_limits(), risk_limits);
+            assert_eq!(fund.timelock(), TimelockConfig::default());
+
+            ink::env::test::set_caller::<Environment>(user);
+            fund.transfer(buyer, 250, vec![]).unwrap();
+            assert_eq!(fund.balance_of(buyer), 250);
+        }
+
+        #[ink::test]
+        fn migrate_turns_the_v3_manager_lock_into_vesting() {
+            let manager = AccountId::from([0x01; 32]);
+            let user = AccountId::from([0x04; 32]);
+            ink::env::test::set_callee::<Environment>(AccountId::from([0xAA; 32]));
+            let mut fund = create_fund_no_wallet(manager, 1_000, false);
+            megafund_wallet(manager);
+            ink::env::pay_with_call!(fund.fund(), 250).unwrap();
+            ink::env::test::set_caller::<Environment>(user);
+            megafund_wallet(user);
+            ink::env::pay_with_call!(fund.fund(), 750).unwrap();
 
-            // Get the % of the fund that the user owns & calculate dividend from the sum
-            let buffer = 1_000_000_000_000;
-            let percentage = (user_balance * buffer) / self.total_supply;
-            let dividend = (sum * percentage) / buffer;
+            // A version 3 fund locked the manager's shares with a flag
+            fund.storage_version.set(&3);
+            fund.lock_manager_shares = true;
+            assert_eq!(fund.migrate(), Ok(STORAGE_VERSION));
+            assert!(!fund.lock_manager_shares);
 
-            dividend
+            // The shares stay locked until liquidation
+            ink::env::test::set_caller::<Environment>(manager);
+            ink::env::test::set_block_timestamp::<Environment>(10 * YEAR);
+            assert!(fund.manager_is_locked());
+            assert_eq!(fund.manager_locked_shares(), 250);
+            assert_eq!(
+                fund.transfer(user, 1, vec![]),
+                Err(Error::ManagerSharesAreLocked.into())
+            );
+            fund.liquidate().unwrap();
+            assert_eq!(fund.manager_unlocked_shares(), 250);
         }
 
-        #[ink(message)]
-        pub fn last_dividend_claim(&self, user: AccountId) -> Timestamp {
-            self.last_claimed_dividend.get(user).unwrap_or(0)
-        }
+        #[ink::test]
+        fn migrate_drops_v2_pool_share_dividends() {
+            let manager = AccountId::from([0x01; 32]);
+            let user = AccountId::from([0x04; 32]);
+            let mut fund = create_funded_fund(manager, user);
+            fund.record_dividend(ZeitgeistAsset::Ztg, 1_000);
 
-        /// The AccountId of the dividend wallet that this fund uses.
-        #[ink(message)]
-        pub fn dividend_wallet(&self) -> AccountId {
-            self.dividend_wallet.to_account_id()
-        }
+            // Version 2 wrote a pool share without its pool id, which the current encoding
+            // can't read
+            let key = StorageKey::key(&fund.dividend_asset_list);
+            ink::env::set_contract_storage(&(key, 1u32), &LegacyZeitgeistAsset::PoolShare);
+            ink::env::set_contract_storage(&(key, 2u32), &LegacyZeitgeistAsset::ForeignAsset(1));
+            fund.dividend_asset_count.set(&3);
+            fund.storage_version.set(&2);
 
-        /// The AccountId of the fund registered by the dividend wallet that this fund uses.
-        /// It should be the same as this smart contract. Otherwise, dividends may be handled
-        /// by a third party smart contract or address.
-        #[ink(message)]
-        pub fn dividend_wallet_fund(&self) -> AccountId {
-            self.dividend_wallet.fund()
+            assert_eq!(fund.migrate(), Ok(STORAGE_VERSION));
+            assert_eq!(
+                fund.dividend_assets(),
+                [ZeitgeistAsset::Ztg, ZeitgeistAsset::ForeignAsset(1)]
+            );
+            assert_eq!(fund.calc_dividend(user), 750);
         }
 
-        // endregion
+        #[ink::test]
+        fn legacy_assets_decode_from_the_old_encoding() {
+            use scale::Decode;
 
-        #[inline]
-        fn only_manager(&self) -> Result<()> {
-            if self.env().caller() != self.manager {
-                return Err(Error::OnlyManagerAllowed);
-            }
-            Ok(())
-        }
+            // The unit pool share of version 2 is a single byte
+            let legacy = LegacyZeitgeistAsset::decode(&mut &[3u8][..]).unwrap();
+            assert_eq!(legacy, LegacyZeitgeistAsset::PoolShare);
+            assert_eq!(legacy.upgrade(), None);
+            assert!(ZeitgeistAsset::decode(&mut &[3u8][..]).is_err());
 
-        /// The shares that the manager owns. Should be high so that they have some skin in
-        /// the game!
-        #[ink(message)]
-        pub fn manager_shares(&self) -> u128 {
-            self.balance_of(self.manager)
+            // Every other asset is encoded the same way
+            let outcome = ZeitgeistAsset::CategoricalOutcome(7, 1);
+            let encoded = scale::Encode::encode(&outcome);
+            let legacy = LegacyZeitgeistAsset::decode(&mut &encoded[..]).unwrap();
+            assert_eq!(legacy.upgrade(), Some(outcome));
+            let position = LegacyPosition::decode(&mut &[2u8, 3][..]).unwrap();
+            assert_eq!(position.upgrade(), None);
         }
 
-        /// If true, the manager cannot transfer their shares (and thus cannot easily rug).
-        #[ink(message)]
-        pub fn manager_is_locked(&self) -> bool {
-            self.lock_manager_shares
+        #[ink::test]
+        fn upgrades_are_proposed_by_shareholders() {
+            let manager = AccountId::from([0x01; 32]);
+            let user = AccountId::from([0x04; 32]);
+            let outsider = AccountId::from([0x05; 32]);
+            let code_hash = Hash::from([0x07; 32]);
+            let mut fund = create_funded_fund(manager, user);
+
+            ink::env::test::set_caller::<Environment>(outsider);
+            assert_eq!(fund.propose_upgrade(code_hash), Err(Error::NoVotingPower));
+
+            ink::env::test::set_caller::<Environment>(user);
+            let id = fund.propose_upgrade(code_hash).unwrap();
+            assert_eq!(
+                fund.manager_proposal(id).unwrap().action,
+                ProposalAction::Upgrade(code_hash)
+            );
+            let id = fund.propose_dividend_wallet_upgrade(code_hash).unwrap();
+            assert_eq!(
+                fund.manager_proposal(id).unwrap().action,
+                ProposalAction::UpgradeDividendWallet(code_hash)
+            );
+
+            // Upgrades wait for quorum like any other proposal
+            assert_eq!(
+                fund.execute_manager_replacement(id),
+                Err(Error::QuorumNotReached)
+            );
         }
-    }
 
-    /// Unit tests in Rust are normally defined within such a `#[cfg(test)]`
-    /// module and test functions are marked with a `#[test]` attribute.
-    /// The below code is technically just normal Rust code.
-    #[cfg(test)]
-    mod tests {
-        // Imports all the definitions from the outer scope so we can use them here.
-        // use super::*;
+        #[ink::test]
+        fn committee_calls_need_enough_approvals() {
+            let manager = AccountId::from([0x01; 32]);
+            let user = AccountId::from([0x04; 32]);
+            let alice = AccountId::from([0x0C; 32]);
+            let bob = AccountId::from([0x0D; 32]);
+            let carol = AccountId::from([0x0E; 32]);
+            let mut fund = create_timelocked_fund(manager, user);
+            let buy = PredictionMarketsCall::BuyCompleteSet {
+                market_id: 1,
+                amount: 500,
+            };
 
-        // TODO: write tests if you have time
+            ink::env::test::set_caller::<Environment>(user);
+            assert_eq!(
+                fund.enable_committee(vec![alice, bob], 1),
+                Err(Error::OnlyManagerAllowed)
+            );
+            ink::env::test::set_caller::<Environment>(manager);
+            assert_eq!(
+                fund.enable_committee(vec![alice, alice], 1),
+                Err(Error::InvalidCommittee)
+            );
+            assert_eq!(
+                fund.enable_committee(vec![alice, bob, carol], 4),
+                Err(Error::InvalidCommittee)
+            );
+            assert_eq!(fund.enable_committee(vec![alice, bob, carol], 2), Ok(()));
+            assert_eq!(
+                fund.enable_committee(vec![alice], 1),
+                Err(Error::CommitteeAlreadyEnabled)
+            );
 
-        use super::ZeitFund;
-        use crate::zeit_fund::{Environment, Error};
-        use ink::primitives::AccountId;
+            // The manager can no longer act alone
+            assert_eq!(
+                fund.prediction_market_call(buy.clone()),
+                Err(Error::CommitteeApprovalRequired)
+            );
+            assert_eq!(
+                fund.issue_dividend(ZeitgeistAsset::Ztg, 10),
+                Err(Error::CommitteeApprovalRequired)
+            );
+            assert_eq!(
+                fund.propose_committee_action(CommitteeAction::Call(
+                    ManagerCall::PredictionMarket(buy.clone())
+                )),
+                Err(Error::NotCommitteeMember)
+            );
 
-        /// Creates a fund without a dividend wallet (for testing purposes).
-        fn create_fund_no_wallet(
-            manager: AccountId,
-            total_shares: u128,
-            lock_manager_shares: bool,
-        ) -> ZeitFund {
-            ZeitFund::no_instantiation(manager, total_shares, lock_manager_shares, manager)
-        }
+            ink::env::test::set_caller::<Environment>(alice);
+            let call = CommitteeAction::Call(ManagerCall::PredictionMarket(buy.clone()));
+            assert_eq!(fund.propose_committee_action(call.clone()), Ok(0));
+            assert_eq!(
+                fund.committee_proposal(0),
+                Some(CommitteeProposal {
+                    proposer: alice,
+                    action: call,
+                    executed: false,
+                })
+            );
+            assert_eq!(fund.committee_approvals(0), 1);
+            assert_eq!(
+                fund.approve_committee_action(0),
+                Err(Error::AlreadyApproved)
+            );
+            assert_eq!(
+                fund.execute_committee_action(0),
+                Err(Error::NotEnoughApprovals)
+            );
 
-        /// Sends a lot of ZTG/DEV to a wallet.
-        fn megafund_wallet(wallet: AccountId) {
-            ink::env::test::set_account_balance::<ink::env::DefaultEnvironment>(
-                wallet,
-                100_000_000_000_000_000,
+            // Revoked approvals no longer count
+            ink::env::test::set_caller::<Environment>(bob);
+            assert_eq!(fund.revoke_committee_approval(0), Err(Error::NotApproved));
+            assert_eq!(fund.approve_committee_action(0), Ok(()));
+            assert!(fund.has_approved(0, bob));
+            assert_eq!(fund.revoke_committee_approval(0), Ok(()));
+            assert_eq!(
+                fund.execute_committee_action(0),
+                Err(Error::NotEnoughApprovals)
+            );
+            assert_eq!(fund.approve_committee_action(0), Ok(()));
+
+            // Large calls still wait for the timelock once approved
+            assert_eq!(fund.execute_committee_action(0), Ok(()));
+            assert_eq!(
+                fund.queued_action(0).map(|action| action.call),
+                Some(ManagerCall::PredictionMarket(buy))
+            );
+            assert!(fund.committee_proposal(0).unwrap().executed);
+            assert_eq!(
+                fund.execute_committee_action(0),
+                Err(Error::ProposalAlreadyExecuted)
+            );
+            assert_eq!(
+                fund.approve_committee_action(1),
+                Err(Error::ProposalNotFound)
             );
         }
 
         #[ink::test]
-        fn funding_works() {
-            let caller = AccountId::from([0x01; 32]);
-            let total_shares = 1_000_000_000_000;
-            let mut contract = create_fund_no_wallet(caller, total_shares, true);
+        fn committee_approves_its_own_changes() {
+            let manager = AccountId::from([0x01; 32]);
+            let user = AccountId::from([0x04; 32]);
+            let alice = AccountId::from([0x0C; 32]);
+            let bob = AccountId::from([0x0D; 32]);
+            let carol = AccountId::from([0x0E; 32]);
+            let mut fund = create_timelocked_fund(manager, user);
 
-            assert_eq!(contract.balance_of(AccountId::from([0; 32])), total_shares);
+            ink::env::test::set_caller::<Environment>(manager);
+            fund.enable_committee(vec![alice, bob], 2).unwrap();
 
-            let half_transfer = 500_000_000_000;
-            megafund_wallet(caller);
+            ink::env::test::set_caller::<Environment>(alice);
+            assert_eq!(
+                fund.propose_committee_action(CommitteeAction::AddMember(bob)),
+                Err(Error::InvalidCommittee)
+            );
+            assert_eq!(
+                fund.propose_committee_action(CommitteeAction::SetThreshold(3)),
+                Err(Error::InvalidCommittee)
+            );
+            let add = fund
+                .propose_committee_action(CommitteeAction::AddMember(carol))
+                .unwrap();
+            ink::env::test::set_caller::<Environment>(bob);
+            fund.approve_committee_action(add).unwrap();
+            fund.execute_committee_action(add).unwrap();
+            assert_eq!(fund.committee(), [alice, bob, carol]);
 
-            // Assert transfers
-            ink::env::pay_with_call!(contract.fund(), half_transfer).unwrap();
-            let balance = contract.balance_of(caller);
-            assert_eq!(balance, half_transfer);
-            ink::env::pay_with_call!(contract.fund(), half_transfer).unwrap();
-            let balance = contract.balance_of(caller);
-            assert_eq!(balance, total_shares);
+            ink::env::test::set_caller::<Environment>(alice);
+            let remove = fund
+                .propose_committee_action(CommitteeAction::RemoveMember(bob))
+                .unwrap();
+            let threshold = fund
+                .propose_committee_action(CommitteeAction::SetThreshold(1))
+                .unwrap();
+            ink::env::test::set_caller::<Environment>(bob);
+            fund.approve_committee_action(remove).unwrap();
+            fund.approve_committee_action(threshold).unwrap();
+            fund.execute_committee_action(remove).unwrap();
+            assert_eq!(fund.committee(), [alice, carol]);
 
-            // Assert that goal is reached
-            assert_eq!(contract.is_funded(), true);
+            // Approvals of removed members no longer count
+            assert_eq!(
+                fund.revoke_committee_approval(threshold),
+                Err(Error::NotCommitteeMember)
+            );
+            ink::env::test::set_caller::<Environment>(alice);
+            assert_eq!(
+                fund.execute_committee_action(threshold),
+                Err(Error::NotEnoughApprovals)
+            );
+            ink::env::test::set_caller::<Environment>(carol);
+            fund.approve_committee_action(threshold).unwrap();
+            fund.execute_committee_action(threshold).unwrap();
+            assert_eq!(fund.committee_threshold(), 1);
 
-            // Assert failure to transfer over
-            ink::env::test::set_account_balance::<ink::env::DefaultEnvironment>(caller, 1);
-            let res = ink::env::pay_with_call!(contract.fund(), 1);
-            assert_eq!(res, Err(Error::FundingTooMuch));
+            // Changes that would leave an invalid committee fail at execution as well
+            let to_two = fund
+                .propose_committee_action(CommitteeAction::SetThreshold(2))
+                .unwrap();
+            let remove = fund
+                .propose_committee_action(CommitteeAction::RemoveMember(alice))
+                .unwrap();
+            fund.execute_committee_action(remove).unwrap();
+            assert_eq!(
+                fund.execute_committee_action(to_two),
+                Err(Error::InvalidCommittee)
+            );
+            assert_eq!(fund.committee(), [carol]);
         }
 
         #[ink::test]
-        fn manager_token_lock_works() {
+        fn roles_are_granted_and_revoked() {
             let manager = AccountId::from([0x01; 32]);
-            let total_shares = 1_000_000_000_000;
-            let mut contract = create_fund_no_wallet(manager, total_shares, true);
+            let user = AccountId::from([0x04; 32]);
+            let trader = AccountId::from([0x0C; 32]);
+            let admin = AccountId::from([0x0D; 32]);
+            let guardian = AccountId::from([0x0E; 32]);
+            let mut fund = create_timelocked_fund(manager, user);
+            let buy = PredictionMarketsCall::BuyCompleteSet {
+                market_id: 1,
+                amount: 500,
+            };
 
-            assert_eq!(contract.balance_of(AccountId::from([0; 32])), total_shares);
-            assert_eq!(contract.manager_is_locked(), true);
+            assert!(fund.has_role(Role::Admin, manager));
+            assert!(fund.has_role(Role::DividendIssuer, manager));
+            assert!(!fund.has_role(Role::Guardian, manager));
+            assert!(fund.has_role(Role::Guardian, test_guardian()));
 
-            // Manager will fund with 50
-            let half_transfer = 500_000_000_000;
-            ink::env::test::set_account_balance::<ink::env::DefaultEnvironment>(
-                manager,
-                half_transfer,
+            ink::env::test::set_caller::<Environment>(trader);
+            assert_eq!(
+                fund.prediction_market_call(buy.clone()),
+                Err(Error::MissingRole(Role::Trader))
+            );
+            assert_eq!(
+                fund.grant_role(Role::Trader, trader),
+                Err(Error::MissingRole(Role::Admin))
             );
-            ink::env::pay_with_call!(contract.fund(), half_transfer).unwrap();
-            assert_eq!(contract.balance_of(manager), half_transfer);
-            assert_eq!(contract.manager_shares(), half_transfer);
 
-            // Assert that the manager can't transfer
-            let res = contract.transfer(AccountId::from([0x08; 32]), half_transfer);
-            assert_eq!(res, Err(Error::ManagerSharesAreLocked));
+            // The manager delegates administration, which can't reach the guardian role
+            ink::env::test::set_caller::<Environment>(manager);
+            assert_eq!(fund.grant_role(Role::Admin, admin), Ok(()));
+            ink::env::test::set_caller::<Environment>(admin);
+            assert_eq!(fund.grant_role(Role::Trader, trader), Ok(()));
+            assert_eq!(
+                fund.grant_role(Role::Guardian, admin),
+                Err(Error::MissingRole(Role::Guardian))
+            );
+
+            // Traders trade, but can't issue dividends
+            ink::env::test::set_caller::<Environment>(trader);
+            assert_eq!(fund.prediction_market_call(buy), Ok(()));
+            assert_eq!(fund.queued_action_count(), 1);
+            assert_eq!(
+                fund.issue_dividend(ZeitgeistAsset::Ztg, 10),
+                Err(Error::MissingRole(Role::DividendIssuer))
+            );
+            ink::env::test::set_block_timestamp::<Environment>(1_010);
+            ink::env::test::set_caller::<Environment>(admin);
+            assert_eq!(
+                fund.execute_action(0),
+                Err(Error::MissingRole(Role::Trader))
+            );
+            assert_eq!(fund.revoke_role(Role::Trader, trader), Ok(()));
+            assert!(!fund.has_role(Role::Trader, trader));
+            assert_eq!(fund.revoke_role(Role::Admin, manager), Ok(()));
+            assert!(fund.has_role(Role::Admin, manager));
+
+            // The guardian shares its role
+            ink::env::test::set_caller::<Environment>(test_guardian());
+            assert_eq!(fund.grant_role(Role::Guardian, guardian), Ok(()));
+            ink::env::test::set_caller::<Environment>(guardian);
+            assert_eq!(fund.pause(), Ok(()));
+            assert_eq!(fund.set_guardian(guardian), Err(Error::OnlyGuardianAllowed));
         }
 
         #[ink::test]
-        fn token_based_dividend_calculation_works() {
+        fn manager_rotation_moves_the_locked_shares() {
             let manager = AccountId::from([0x01; 32]);
             let user = AccountId::from([0x04; 32]);
-            let total_shares = 100_000_000_000;
-            let mut fund = create_fund_no_wallet(manager, total_shares, false);
-
-            // Manager will fund with 1/4 of the shares
-            let quarter_transfer = total_shares / 4;
-            megafund_wallet(manager);
-            ink::env::pay_with_call!(fund.fund(), quarter_transfer).unwrap();
-            assert_eq!(fund.balance_of(manager), quarter_transfer);
-            assert_eq!(fund.manager_shares(), quarter_transfer);
+            let new_manager = AccountId::from([0x0C; 32]);
+            let mut fund = create_funded_fund(manager, user);
+            assert!(fund.manager_is_locked());
+            assert_eq!(fund.manager_shares(), 250);
 
-            // Another account will fund with 3/4 of the shares
             ink::env::test::set_caller::<Environment>(user);
-            megafund_wallet(user);
-            ink::env::pay_with_call!(fund.fund(), quarter_transfer * 3).unwrap();
-            assert_eq!(fund.balance_of(user), quarter_transfer * 3);
-            assert!(fund.is_funded());
-
-            // NOTE:    Cannot do fund.issue_dividend() since it calls runtime. Instead,
-            //          we manually add to the dividend.
+            assert_eq!(fund.propose_manager(user), Err(Error::OnlyManagerAllowed));
+            assert_eq!(fund.accept_manager(), Err(Error::NotPendingManager));
 
-            // "Issue" dividend by cheating
-            let dividend_amount = total_shares / 2;
-            fund.dividends.push((100_000_000, dividend_amount));
+            ink::env::test::set_caller::<Environment>(manager);
+            assert_eq!(fund.propose_manager(new_manager), Ok(()));
+            assert_eq!(fund.pending_manager(), Some(new_manager));
+            assert_eq!(fund.manager(), manager);
 
-            // Claim values should be proportional to the tokens
-            let manager_dividend = fund.calc_dividend(manager);
-            assert_eq!(manager_dividend, dividend_amount / 4);
-            let user_dividend = fund.calc_dividend(user);
-            assert_eq!(user_dividend, dividend_amount / 4 * 3);
+            // The manager can cancel until the rotation is accepted
+            assert_eq!(fund.propose_manager(manager), Ok(()));
+            ink::env::test::set_caller::<Environment>(new_manager);
+            assert_eq!(fund.accept_manager(), Err(Error::NotPendingManager));
+            ink::env::test::set_caller::<Environment>(manager);
+            fund.propose_manager(new_manager).unwrap();
 
-            // "Issue" second dividend by cheating
-            let second_dividend_amount = total_shares / 4;
-            fund.dividends.push((100_000_000, second_dividend_amount));
+            // The key can be rotated while the guardian has the fund paused
+            ink::env::test::set_caller::<Environment>(test_guardian());
+            fund.pause().unwrap();
+            ink::env::test::set_caller::<Environment>(new_manager);
+            assert_eq!(fund.accept_manager(), Ok(()));
+            assert_eq!(fund.manager(), new_manager);
+            assert_eq!(fund.pending_manager(), None);
+            assert_eq!(fund.balance_of(manager), 0);
+            assert_eq!(fund.manager_shares(), 250);
+            assert!(fund.has_role(Role::Trader, new_manager));
+            assert!(!fund.has_role(Role::Trader, manager));
+            ink::env::test::set_caller::<Environment>(test_guardian());
+            fund.unpause().unwrap();
+            ink::env::test::set_caller::<Environment>(new_manager);
 
-            // Claim values should sum up
-            let manager_dividend = fund.calc_dividend(manager);
+            // The shares stay locked under the new key
             assert_eq!(
-                manager_dividend,
-                (dividend_amount + second_dividend_amount) / 4
+                fund.transfer(user, 1, vec![]),
+                Err(Error::ManagerSharesAreLocked.into())
             );
-            let user_dividend = fund.calc_dividend(user);
+
+            // The previous key is no longer the manager and its new shares are not locked
+            ink::env::test::set_caller::<Environment>(user);
+            fund.transfer(manager, 50, vec![]).unwrap();
+            ink::env::test::set_caller::<Environment>(manager);
+            assert_eq!(fund.transfer(user, 50, vec![]), Ok(()));
             assert_eq!(
-                user_dividend,
-                (dividend_amount + second_dividend_amount) / 4 * 3
+                fund.propose_manager(manager),
+                Err(Error::OnlyManagerAllowed)
             );
         }
     }
 }
 
+/// Predicts the address that `pallet-contracts` gives a contract instantiated by `deployer`
+/// from `code_hash`, where `input_data` is the constructor selector followed by its arguments.
+///
+/// https://github.com/paritytech/polkadot-sdk/blob/master/substrate/frame/contracts/src/address.rs
+pub fn contract_address(
+    deployer: &AccountId,
+    code_hash: &Hash,
+    input_data: &[u8],
+    salt: &[u8],
+) -> AccountId {
+    let mut output = [0; 32];
+    ink::env::hash_encoded::<ink::env::hash::Blake2x256, _>(
+        &(b"contract_addr_v1", deployer, code_hash, input_data, salt),
+        &mut output,
+    );
+    AccountId::from(output)
+}
+
+/// Predicts the address of the dividend wallet that `ZeitFund::new` instantiates for the fund
+/// at `fund`, so that front-ends can know it before deployment.
+pub fn predict_dividend_wallet_address(fund: &AccountId, wallet_code_hash: &Hash) -> AccountId {
+    contract_address(
+        fund,
+        wallet_code_hash,
+        &ink::selector_bytes!("new"),
+        fund.as_ref(),
+    )
+}
+
+/// The PSP22 error types.
+#[derive(Debug, PartialEq, Eq, scale::Encode, scale::Decode)]
+#[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+pub enum PSP22Error {
+    /// Returned for errors that are specific to the implementation.
+    Custom(String),
+    /// Returned if not enough balance to fulfill a request is available.
+    InsufficientBalance,
+    /// Returned if not enough allowance to fulfill a request is available.
+    InsufficientAllowance,
+    /// Returned if the recipient's address is zero.
+    ZeroRecipientAddress,
+    /// Returned if the sender's address is zero.
+    ZeroSenderAddress,
+    /// Returned if a safe transfer check fails.
+    SafeTransferCheckFailed(String),
+}
+
+/// The PSP22 fungible token standard, so that wallets, DEXes and other contracts can use fund
+/// shares without a custom ABI.
+///
+/// https://github.com/w3f/PSPs/blob/master/PSPs/psp-22.md
+#[ink::trait_definition]
+pub trait PSP22 {
+    #[ink(message)]
+    fn total_supply(&self) -> u128;
+
+    #[ink(message)]
+    fn balance_of(&self, owner: AccountId) -> u128;
+
+    #[ink(message)]
+    fn allowance(&self, owner: AccountId, spender: AccountId) -> u128;
+
+    #[ink(message)]
+    fn transfer(&mut self, to: AccountId, value: u128, data: Vec<u8>) -> Result<(), PSP22Error>;
+
+    #[ink(message)]
+    fn transfer_from(
+        &mut self,
+        from: AccountId,
+        to: AccountId,
+        value: u128,
+        data: Vec<u8>,
+    ) -> Result<(), PSP22Error>;
+
+    #[ink(message)]
+    fn approve(&mut self, spender: AccountId, value: u128) -> Result<(), PSP22Error>;
+
+    #[ink(message)]
+    fn increase_allowance(
+        &mut self,
+        spender: AccountId,
+        delta_value: u128,
+    ) -> Result<(), PSP22Error>;
+
+    #[ink(message)]
+    fn decrease_allowance(
+        &mut self,
+        spender: AccountId,
+        delta_value: u128,
+    ) -> Result<(), PSP22Error>;
+}
+
+/// The optional metadata extension of PSP22.
+#[ink::trait_definition]
+pub trait PSP22Metadata {
+    #[ink(message)]
+    fn token_name(&self) -> Option<String>;
+
+    #[ink(message)]
+    fn token_symbol(&self) -> Option<String>;
+
+    #[ink(message)]
+    fn token_decimals(&self) -> u8;
+}
+
 #[derive(scale::Encode, scale::Decode)]
 pub enum RuntimeCall {
     /// This index can be found by investigating runtime configuration. You can check the
@@ -727,8 +6953,11 @@ pub enum AssetManagerCall {
     },
 }
 
-#[derive(scale::Encode, scale::Decode)]
-#[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+#[derive(scale::Encode, scale::Decode, Clone, PartialEq)]
+#[cfg_attr(
+    feature = "std",
+    derive(Debug, scale_info::TypeInfo, ink::storage::traits::StorageLayout)
+)]
 pub enum SwapsCall {
     #[codec(index = 1)]
     PoolExit {
@@ -771,8 +7000,23 @@ pub enum SwapsCall {
     },
 }
 
-#[derive(scale::Encode, scale::Decode)]
-#[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+impl SwapsCall {
+    /// True if the call moves the fund's value back into ZTG.
+    pub fn is_exit(&self) -> bool {
+        match self {
+            SwapsCall::PoolExit { .. } => true,
+            SwapsCall::PoolJoin { .. } => false,
+            SwapsCall::SwapExactAmountIn { asset_out, .. }
+            | SwapsCall::SwapExactAmountOut { asset_out, .. } => asset_out == &ZeitgeistAsset::Ztg,
+        }
+    }
+}
+
+#[derive(scale::Encode, scale::Decode, Clone, PartialEq)]
+#[cfg_attr(
+    feature = "std",
+    derive(Debug, scale_info::TypeInfo, ink::storage::traits::StorageLayout)
+)]
 pub enum PredictionMarketsCall {
     #[codec(index = 5)]
     BuyCompleteSet {
@@ -795,15 +7039,102 @@ pub enum PredictionMarketsCall {
     },
 }
 
+impl RuntimeCall {
+    /// True if the call moves the fund's value back into ZTG.
+    pub fn is_exit(&self) -> bool {
+        match self {
+            RuntimeCall::AssetManager(_) => false,
+            RuntimeCall::Swaps(call) => call.is_exit(),
+            RuntimeCall::PredictionMarkets(call) => call.is_exit(),
+        }
+    }
+}
+
+impl PredictionMarketsCall {
+    /// True if the call moves the fund's value back into ZTG.
+    pub fn is_exit(&self) -> bool {
+        match self {
+            PredictionMarketsCall::BuyCompleteSet { .. } => false,
+            PredictionMarketsCall::RedeemShares { .. }
+            | PredictionMarketsCall::SellCompleteSet { .. } => true,
+        }
+    }
+}
+
 #[derive(scale::Encode, scale::Decode, Clone, PartialEq)]
-#[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+#[cfg_attr(
+    feature = "std",
+    derive(Debug, scale_info::TypeInfo, ink::storage::traits::StorageLayout)
+)]
 pub enum ZeitgeistAsset {
     CategoricalOutcome(u128, u16),
     ScalarOutcome, //(u128, ScalarPosition),
     CombinatorialOutcome,
-    PoolShare, //(SerdeWrapper<PoolId>),
-    Ztg,       // default
+    PoolShare(u128), //(SerdeWrapper<PoolId>),
+    Ztg,             // default
+    ForeignAsset(u32),
+}
+
+impl ZeitgeistAsset {
+    /// The market that the asset is an outcome of, if any.
+    pub fn market_id(&self) -> Option<u128> {
+        match self {
+            ZeitgeistAsset::CategoricalOutcome(market_id, _) => Some(*market_id),
+            _ => None,
+        }
+    }
+}
+
+/// `ZeitgeistAsset` as funds stored it under storage version 2 and earlier, before `PoolShare`
+/// carried its pool id. Only used to decode and migrate that storage.
+#[derive(scale::Encode, scale::Decode, Clone, PartialEq)]
+#[cfg_attr(
+    feature = "std",
+    derive(Debug, scale_info::TypeInfo, ink::storage::traits::StorageLayout)
+)]
+pub enum LegacyZeitgeistAsset {
+    CategoricalOutcome(u128, u16),
+    ScalarOutcome,
+    CombinatorialOutcome,
+    PoolShare,
+    Ztg,
     ForeignAsset(u32),
 }
 
-// ink::storage::traits::StorageLayout,
+impl LegacyZeitgeistAsset {
+    /// The asset in the current encoding, or none for a pool share, since its pool is unknown.
+    /// The runtime could never transfer such a pool share, so no dividend of it can be claimed.
+    pub fn upgrade(self) -> Option<ZeitgeistAsset> {
+        match self {
+            LegacyZeitgeistAsset::CategoricalOutcome(market_id, index) => {
+                Some(ZeitgeistAsset::CategoricalOutcome(market_id, index))
+            }
+            LegacyZeitgeistAsset::ScalarOutcome => Some(ZeitgeistAsset::ScalarOutcome),
+            LegacyZeitgeistAsset::CombinatorialOutcome => {
+                Some(ZeitgeistAsset::CombinatorialOutcome)
+            }
+            LegacyZeitgeistAsset::PoolShare => None,
+            LegacyZeitgeistAsset::Ztg => Some(ZeitgeistAsset::Ztg),
+            LegacyZeitgeistAsset::ForeignAsset(id) => Some(ZeitgeistAsset::ForeignAsset(id)),
+        }
+    }
+}
+
+impl From<ZeitgeistAsset> for dividend_wallet::ZeitgeistAsset {
+    fn from(asset: ZeitgeistAsset) -> Self {
+        match asset {
+            ZeitgeistAsset::CategoricalOutcome(market_id, index) => {
+                dividend_wallet::ZeitgeistAsset::CategoricalOutcome(market_id, index)
+            }
+            ZeitgeistAsset::ScalarOutcome => dividend_wallet::ZeitgeistAsset::ScalarOutcome,
+            ZeitgeistAsset::CombinatorialOutcome => {
+                dividend_wallet::ZeitgeistAsset::CombinatorialOutcome
+            }
+            ZeitgeistAsset::PoolShare(pool_id) => {
+                dividend_wallet::ZeitgeistAsset::PoolShare(pool_id)
+            }
+            ZeitgeistAsset::Ztg => dividend_wallet::ZeitgeistAsset::Ztg,
+            ZeitgeistAsset::ForeignAsset(id) => dividend_wallet::ZeitgeistAsset::ForeignAsset(id),
+        }
+    }
+}